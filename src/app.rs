@@ -1,8 +1,14 @@
+use crate::utils::runtime::{ContainerRuntime, RuntimeSelection};
 use log::LevelFilter;
 
 static VERBOSITY: once_cell::sync::OnceCell<LevelFilter> = once_cell::sync::OnceCell::new();
 static ALWAYS_PULL: once_cell::sync::OnceCell<bool> = once_cell::sync::OnceCell::new();
 static NO_RUN: once_cell::sync::OnceCell<bool> = once_cell::sync::OnceCell::new();
+static STRICT: once_cell::sync::OnceCell<bool> = once_cell::sync::OnceCell::new();
+static RUNTIME_SELECTION: once_cell::sync::OnceCell<RuntimeSelection> =
+    once_cell::sync::OnceCell::new();
+static RUNTIME: once_cell::sync::OnceCell<&'static dyn ContainerRuntime> =
+    once_cell::sync::OnceCell::new();
 
 pub fn verbosity() -> &'static LevelFilter {
     match VERBOSITY.get() {
@@ -37,3 +43,30 @@ pub fn no_run() -> &'static bool {
         None => &false,
     }
 }
+
+pub fn set_global_strict(strict: bool) {
+    STRICT.set(strict).expect("could not set strict")
+}
+
+pub fn strict() -> &'static bool {
+    match STRICT.get() {
+        Some(value) => value,
+        None => &false,
+    }
+}
+
+pub fn set_global_runtime_selection(selection: RuntimeSelection) {
+    RUNTIME_SELECTION
+        .set(selection)
+        .expect("could not set runtime selection")
+}
+
+/// Returns the container runtime chosen with `--runtime`, resolved on first use so commands that
+/// never run compose don't need one installed. An error if the runtime is not installed.
+pub fn runtime() -> anyhow::Result<&'static dyn ContainerRuntime> {
+    let selection = RUNTIME_SELECTION
+        .get()
+        .copied()
+        .unwrap_or(RuntimeSelection::Auto);
+    RUNTIME.get_or_try_init(|| selection.resolve()).copied()
+}
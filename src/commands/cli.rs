@@ -1,10 +1,19 @@
 use crate::commands::delete::Delete;
+use crate::commands::diff::Diff;
+use crate::commands::get::Get;
+use crate::commands::history::History;
 use crate::commands::install::Install;
+use crate::commands::lifecycle::{Restart, Start, Stop};
+use crate::commands::lint::Lint;
 use crate::commands::list::List;
+use crate::commands::logs::Logs;
+use crate::commands::rollback::Rollback;
+use crate::commands::status::Status;
 use crate::commands::template::Template;
 use crate::commands::test::Test;
 use crate::commands::upgrade::Upgrade;
-use clap::{Parser, Subcommand};
+use crate::utils::runtime::RuntimeSelection;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(version, bin_name = "composer")]
@@ -18,10 +27,25 @@ pub struct Cli {
     /// If included, docker compose up command is omitted
     #[clap(short, long)]
     pub no_run: bool,
+    /// If included, rendering fails on any undefined variable instead of rendering it as empty.
+    ///   Can also be enabled per application by setting strict: true in app.yaml
+    #[clap(long)]
+    pub strict: bool,
+    /// The container runtime used to run compose files, auto uses the first installed of docker
+    ///   compose, podman compose and docker-compose
+    #[clap(long, value_enum, default_value_t = RuntimeSelection::Auto)]
+    pub runtime: RuntimeSelection,
     #[clap(subcommand)]
     cmd: Cmd,
 }
 
+/// Output formats for commands that support machine readable output
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Cmd {
     // Triple slashes are used for help text in the CLI
@@ -43,11 +67,53 @@ pub enum Cmd {
     ///   completely.
     #[clap(alias = "d", alias = "uninstall")]
     Delete(Delete),
+    /// Rolls an application back to a previously recorded revision, defaults to the
+    ///   revision before the current one.
+    #[clap(alias = "r")]
+    Rollback(Rollback),
+    /// Lists every recorded install, upgrade and rollback of an application
+    #[clap(alias = "h")]
+    History(History),
+    /// Shows the live state of every service of an application, including its image, health,
+    ///   published ports and restart count.
+    #[clap(alias = "s")]
+    Status(Status),
+    /// Prints the container logs of an application, optionally for a single service.
+    Logs(Logs),
+    /// Stops the containers of the given application(s) (by id unless using --all) without
+    ///   removing them, the application is marked as STOPPED.
+    Stop(Stop),
+    /// Starts the containers of the given stopped application(s) (by id unless using --all).
+    Start(Start),
+    /// Restarts the containers of the given application(s) (by id unless using --all).
+    Restart(Restart),
+    /// Renders an application with new (or its stored) values into a temporary directory and
+    ///   prints a unified diff against the currently installed files, previewing an upgrade.
+    Diff(Diff),
+    /// Checks a template directory without installing it, reporting every problem found with
+    ///   app.yaml, the templates and the rendered compose files. Exits with 1 if any are found.
+    Lint(Lint),
+    /// Prints the consolidated values or the rendered compose files an installed application was
+    ///   deployed with.
+    Get(Get),
     // Hidden test function
     Test(Test),
 }
 
 impl Cli {
+    /// Whether the command needs docker compose to be installed, linting, templating and dry runs
+    ///   only render so can be run anywhere, e.g. in CI, and get, history and list without probing
+    ///   only read what composer has stored
+    pub fn requires_compose(&self) -> bool {
+        match &self.cmd {
+            Cmd::Install(install) => !install.dry_run,
+            Cmd::Upgrade(upgrade) => !upgrade.dry_run,
+            Cmd::List(list) => list.probes(),
+            Cmd::Lint(_) | Cmd::Get(_) | Cmd::History(_) | Cmd::Template(_) => false,
+            _ => true,
+        }
+    }
+
     pub fn run(&self) -> anyhow::Result<()> {
         match &self.cmd {
             Cmd::Install(install) => install.exec()?,
@@ -56,7 +122,44 @@ impl Cli {
             Cmd::Test(test) => test.exec()?,
             Cmd::Template(template) => template.exec()?,
             Cmd::Delete(delete) => delete.exec()?,
+            Cmd::Rollback(rollback) => rollback.exec()?,
+            Cmd::History(history) => history.exec()?,
+            Cmd::Status(status) => status.exec()?,
+            Cmd::Logs(logs) => logs.exec()?,
+            Cmd::Stop(stop) => stop.exec()?,
+            Cmd::Start(start) => start.exec()?,
+            Cmd::Restart(restart) => restart.exec()?,
+            Cmd::Diff(diff) => diff.exec()?,
+            Cmd::Lint(lint) => lint.exec()?,
+            Cmd::Get(get) => get.exec()?,
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requires_compose(args: &[&str]) -> bool {
+        let mut command_line = vec!["composer"];
+        command_line.extend_from_slice(args);
+        Cli::parse_from(command_line).requires_compose()
+    }
+
+    #[test]
+    fn test_requires_compose() {
+        trace!("Running test_requires_compose.");
+        assert!(requires_compose(&["install", "template"]));
+        assert!(requires_compose(&["upgrade", "template"]));
+        assert!(requires_compose(&["delete", "app"]));
+        assert!(!requires_compose(&["install", "template", "--dry-run"]));
+        assert!(!requires_compose(&["upgrade", "template", "--dry-run"]));
+        assert!(!requires_compose(&["template", "-t", "template.jinja2"]));
+        assert!(!requires_compose(&["lint", "template"]));
+        assert!(requires_compose(&["list"]));
+        assert!(!requires_compose(&["list", "--no-probe"]));
+        assert!(!requires_compose(&["list", "--quiet"]));
+        assert!(!requires_compose(&["history", "app"]));
+    }
+}
@@ -1,11 +1,8 @@
-use crate::utils::copy_file_utils::get_composer_directory;
-use crate::utils::docker_compose::compose_down;
+use crate::utils::docker_compose::{compose_down, get_compose_files_by_id};
 use crate::utils::storage::read_from::{get_all_from_storage, if_application_exists};
 use crate::utils::storage::write_to_storage::delete_application_by_id;
-use crate::utils::walk::get_files_with_name;
 use anyhow::anyhow;
 use clap::Args;
-use std::path::PathBuf;
 
 #[derive(Debug, Args)]
 pub struct Delete {
@@ -19,16 +16,8 @@ pub struct Delete {
 
 // Call docker compose down on all docker-compose.jinja2 files for this application
 fn compose_down_by_id(id: &str) -> anyhow::Result<()> {
-    // Ensure the .composer directory exists
-    let composer_directory = get_composer_directory()?;
-    let composer_id_directory: PathBuf = composer_directory.join(id);
-    // Find all docker-compose.jinja2 files
-    let all_compose_files = get_files_with_name(
-        composer_id_directory.to_str().unwrap(),
-        "docker-compose.jinja2",
-    );
-    for compose_file in all_compose_files {
-        compose_down(&compose_file, id);
+    for compose_file in get_compose_files_by_id(id)? {
+        compose_down(&compose_file, id)?;
     }
     Ok(())
 }
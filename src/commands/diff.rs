@@ -0,0 +1,413 @@
+use crate::commands::install::render_application;
+use crate::commands::upgrade::get_previous_values;
+use crate::utils::copy_file_utils::get_composer_directory;
+use crate::utils::load_values::ValueOptions;
+use crate::utils::storage::read_from::get_application_by_id;
+use anyhow::anyhow;
+use clap::Args;
+use serde_yaml::Value;
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[derive(Debug, Args)]
+pub struct Diff {
+    /// The id of the installed application to compare against
+    #[clap(index = 1)]
+    pub id: String,
+    /// The template directory to render, defaults to the directory the application was installed from
+    #[clap(index = 2)]
+    pub directory: Option<PathBuf>,
+    // The values to render with, defaults to the values the application was installed with
+    #[clap(flatten)]
+    pub values: ValueOptions,
+    /// The number of unchanged lines shown around each change
+    #[clap(short = 'U', long, default_value_t = 3)]
+    pub context: usize,
+}
+
+// Returns every file under a directory relative to it, sorted
+fn get_relative_files(directory: &Path) -> BTreeSet<PathBuf> {
+    WalkDir::new(directory)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .strip_prefix(directory)
+                .ok()
+                .map(|relative| relative.to_path_buf())
+        })
+        .collect()
+}
+
+fn read_file(path: &Path) -> Option<Vec<u8>> {
+    fs::read(path).ok()
+}
+
+fn print_unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) {
+    use owo_colors::OwoColorize;
+    let diff = TextDiff::from_lines(old, new);
+    let unified = diff
+        .unified_diff()
+        .context_radius(context)
+        .header(old_name, new_name)
+        .to_string();
+    for line in unified.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!(
+                "{}",
+                line.if_supports_color(owo_colors::Stream::Stdout, |text| text.bold())
+            );
+        } else if line.starts_with('+') {
+            println!(
+                "{}",
+                line.if_supports_color(owo_colors::Stream::Stdout, |text| text.green())
+            );
+        } else if line.starts_with('-') {
+            println!(
+                "{}",
+                line.if_supports_color(owo_colors::Stream::Stdout, |text| text.red())
+            );
+        } else if line.starts_with("@@") {
+            println!(
+                "{}",
+                line.if_supports_color(owo_colors::Stream::Stdout, |text| text.cyan())
+            );
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+fn get_services(compose: &str) -> serde_yaml::Mapping {
+    match serde_yaml::from_str::<Value>(compose) {
+        Ok(value) => match value.get("services") {
+            Some(Value::Mapping(services)) => services.clone(),
+            _ => serde_yaml::Mapping::new(),
+        },
+        Err(_) => serde_yaml::Mapping::new(),
+    }
+}
+
+fn value_to_string(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(string)) => string.clone(),
+        Some(value) => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        None => "<none>".to_string(),
+    }
+}
+
+/// Summarises how the services of a compose file change, reporting added and removed services
+/// as well as changes to the image, environment and ports of existing services.
+fn summarise_service_changes(old_compose: &str, new_compose: &str) -> Vec<String> {
+    let old_services = get_services(old_compose);
+    let new_services = get_services(new_compose);
+    let mut changes = Vec::new();
+    for (name, old_service) in &old_services {
+        let name_str = value_to_string(Some(name));
+        match new_services.get(name) {
+            None => changes.push(format!("- {}: removed", name_str)),
+            Some(new_service) => {
+                if old_service.get("image") != new_service.get("image") {
+                    changes.push(format!(
+                        "~ {}: image {} -> {}",
+                        name_str,
+                        value_to_string(old_service.get("image")),
+                        value_to_string(new_service.get("image"))
+                    ));
+                }
+                if old_service.get("environment") != new_service.get("environment") {
+                    changes.push(format!("~ {}: environment changed", name_str));
+                }
+                if old_service.get("ports") != new_service.get("ports") {
+                    changes.push(format!("~ {}: ports changed", name_str));
+                }
+            }
+        }
+    }
+    for (name, new_service) in &new_services {
+        if !old_services.contains_key(name) {
+            changes.push(format!(
+                "+ {}: added with image {}",
+                value_to_string(Some(name)),
+                value_to_string(new_service.get("image"))
+            ));
+        }
+    }
+    changes
+}
+
+/// How a file differs between the installed application and the newly rendered one
+#[derive(Debug, Clone, PartialEq)]
+enum FileChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A file that differs between the installed application and the newly rendered one
+#[derive(Debug, Clone)]
+struct FileChange {
+    path: PathBuf,
+    kind: FileChangeKind,
+    old_content: Option<Vec<u8>>,
+    new_content: Option<Vec<u8>>,
+}
+
+impl FileChange {
+    // Returns the old and new content as text, or None if either of them is binary
+    fn get_text(&self) -> Option<(String, String)> {
+        let to_text = |content: &Option<Vec<u8>>| match content {
+            Some(content) => String::from_utf8(content.clone()).ok(),
+            None => Some(String::new()),
+        };
+        Some((to_text(&self.old_content)?, to_text(&self.new_content)?))
+    }
+}
+
+// Compares every file of the installed application with the newly rendered one
+fn get_file_changes(current_directory: &Path, rendered_directory: &Path) -> Vec<FileChange> {
+    let current_files = get_relative_files(current_directory);
+    let rendered_files = get_relative_files(rendered_directory);
+    let mut changes = Vec::new();
+    for file in current_files.union(&rendered_files) {
+        let old_content = read_file(&current_directory.join(file));
+        let new_content = read_file(&rendered_directory.join(file));
+        let kind = match (&old_content, &new_content) {
+            (old_content, new_content) if old_content == new_content => continue,
+            (Some(_), None) => FileChangeKind::Removed,
+            (None, Some(_)) => FileChangeKind::Added,
+            _ => FileChangeKind::Changed,
+        };
+        changes.push(FileChange {
+            path: file.clone(),
+            kind,
+            old_content,
+            new_content,
+        });
+    }
+    changes
+}
+
+// Summarises the service changes of every changed compose file, naming the file of each change
+fn get_service_changes(changes: &[FileChange]) -> Vec<String> {
+    let mut service_changes = Vec::new();
+    for change in changes {
+        if change.path.file_name() != Some("docker-compose.jinja2".as_ref()) {
+            continue;
+        }
+        if let Some((old_text, new_text)) = change.get_text() {
+            service_changes.extend(
+                summarise_service_changes(&old_text, &new_text)
+                    .into_iter()
+                    .map(|summary| format!("{} ({})", summary, change.path.display())),
+            );
+        }
+    }
+    service_changes
+}
+
+impl Diff {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        let changes = self.get_changes()?;
+        if changes.is_empty() {
+            info!("No changes for application {}", self.id);
+            return Ok(());
+        }
+        for change in &changes {
+            let file_name = change.path.to_string_lossy();
+            match change.kind {
+                FileChangeKind::Removed => info!("Removed file {}", file_name),
+                FileChangeKind::Added => info!("Added file {}", file_name),
+                FileChangeKind::Changed => info!("Changed file {}", file_name),
+            }
+            match change.get_text() {
+                Some((old_text, new_text)) => print_unified_diff(
+                    &old_text,
+                    &new_text,
+                    &format!("a/{}", file_name),
+                    &format!("b/{}", file_name),
+                    self.context,
+                ),
+                None => println!("Binary file {} differs", file_name),
+            }
+        }
+        let service_changes = get_service_changes(&changes);
+        if !service_changes.is_empty() {
+            info!("Service changes:");
+            for change in service_changes {
+                info_no_bold!("  {}", change);
+            }
+        }
+        Ok(())
+    }
+
+    // Renders the application as an upgrade would and compares it with the installed files
+    fn get_changes(&self) -> anyhow::Result<Vec<FileChange>> {
+        let application = get_application_by_id(&self.id)?;
+        let directory = self
+            .directory
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&application.compose_path));
+        // Preview what an upgrade with the same values would do
+        let (values, reused) = if self.values.is_empty() {
+            get_previous_values(&application, &self.values)?
+        } else {
+            (self.values.clone(), None)
+        };
+        if reused.is_none() && values.with_default_values(&directory).is_empty() {
+            return Err(anyhow!(
+                "Cannot diff application '{}' because no value files were provided and none were found from the previous installation. Use -v <values path> to specify value files.",
+                self.id
+            ));
+        }
+
+        let consolidated_values = values
+            .read_with_default_values(&directory, reused.as_ref())?
+            .values()?;
+        let current_directory = get_composer_directory()?.join(&self.id);
+        let rendered_directory = tempfile::tempdir()?;
+        // Render composer.cwd as the installed directory, as an upgrade would
+        render_application(
+            &directory,
+            rendered_directory.path(),
+            &current_directory,
+            &consolidated_values,
+        )?;
+        Ok(get_file_changes(
+            &current_directory,
+            rendered_directory.path(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::install::Install;
+    use crate::utils::test_utils::clean_up_test_folder;
+    use relative_path::RelativePath;
+    use serial_test::serial;
+    use std::env::current_dir;
+
+    #[test]
+    fn test_summarise_service_changes() {
+        let old_compose = r#"
+services:
+  web:
+    image: nginx:1.0
+    ports: ["80:80"]
+    environment:
+      MODE: dev
+  db:
+    image: postgres
+"#;
+        let new_compose = r#"
+services:
+  web:
+    image: nginx:1.1
+    ports: ["8080:80"]
+    environment:
+      MODE: prod
+  cache:
+    image: redis
+"#;
+        let changes = summarise_service_changes(old_compose, new_compose);
+        assert_eq!(
+            changes,
+            vec![
+                "~ web: image nginx:1.0 -> nginx:1.1",
+                "~ web: environment changed",
+                "~ web: ports changed",
+                "- db: removed",
+                "+ cache: added with image redis",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarise_service_changes_unchanged() {
+        let compose = "services:\n  web:\n    image: nginx\n";
+        assert!(summarise_service_changes(compose, compose).is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_diff_installed_application() -> anyhow::Result<()> {
+        trace!("Running test_diff_installed_application.");
+        let id = "test_diff_installed_application";
+        let current_dir = current_dir()?;
+        let install_dir =
+            RelativePath::new("resources/test/diff/v1/").to_logical_path(&current_dir);
+        let upgrade_dir =
+            RelativePath::new("resources/test/diff/v2/").to_logical_path(&current_dir);
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        // The template uses composer.cwd, which must not show up as a change
+        let unchanged = Diff {
+            id: id.to_string(),
+            directory: None,
+            values: ValueOptions::default(),
+            context: 3,
+        }
+        .get_changes();
+        let changed = Diff {
+            id: id.to_string(),
+            directory: Some(upgrade_dir),
+            values: ValueOptions::default(),
+            context: 3,
+        }
+        .get_changes();
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert!(unchanged?.is_empty());
+        let changed = changed?;
+        let files: Vec<(String, FileChangeKind)> = changed
+            .iter()
+            .map(|change| {
+                (
+                    change.path.to_string_lossy().to_string(),
+                    change.kind.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("added.conf".to_string(), FileChangeKind::Added),
+                ("app.yaml".to_string(), FileChangeKind::Changed),
+                ("docker-compose.jinja2".to_string(), FileChangeKind::Changed),
+                ("removed.conf".to_string(), FileChangeKind::Removed),
+                ("values.yaml".to_string(), FileChangeKind::Changed),
+            ]
+        );
+        assert_eq!(
+            get_service_changes(&changed),
+            vec![
+                "~ web: image nginx:1.0 -> nginx:1.1 (docker-compose.jinja2)",
+                "~ web: environment changed (docker-compose.jinja2)",
+                "~ web: ports changed (docker-compose.jinja2)",
+                "- db: removed (docker-compose.jinja2)",
+                "+ cache: added with image redis (docker-compose.jinja2)",
+            ]
+        );
+        let (old_text, new_text) = changed[0].get_text().unwrap();
+        assert_eq!((old_text.as_str(), new_text.as_str()), ("", "added\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::utils::copy_file_utils::get_composer_directory;
+use crate::utils::storage::read_from::get_application_by_id;
+use crate::utils::storage::revisions::{
+    get_revision, get_revision_files_directory, get_revision_values, get_revisions,
+};
+use crate::utils::walk::get_files_with_name;
+use anyhow::{anyhow, Context};
+use clap::{Args, Subcommand};
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Args)]
+pub struct Get {
+    #[clap(subcommand)]
+    pub cmd: GetCmd,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GetCmd {
+    /// Prints the consolidated values an application was deployed with as YAML
+    Values(GetValues),
+    /// Prints the rendered compose files of an application
+    Manifest(GetManifest),
+}
+
+#[derive(Debug, Args)]
+pub struct GetValues {
+    /// The id of the application to print the values of
+    #[clap(index = 1)]
+    pub id: String,
+    /// Print the values of a recorded revision instead of the installed one
+    #[clap(short, long)]
+    pub revision: Option<u32>,
+}
+
+#[derive(Debug, Args)]
+pub struct GetManifest {
+    /// The id of the application to print the rendered compose files of
+    #[clap(index = 1)]
+    pub id: String,
+    /// Print the compose files of a recorded revision instead of the installed ones
+    #[clap(short, long)]
+    pub revision: Option<u32>,
+}
+
+/// Returns the consolidated values an application, or one of its revisions, was deployed with from
+/// its values snapshot. Revisions recorded before values were snapshotted have their values
+/// recomputed from the stored value files and set values layered over the template directory's
+/// values.yaml, without `COMPOSER_VALUE_` environment variables as the application was never
+/// deployed with the ones of the current shell.
+fn get_values(id: &str, revision: Option<u32>) -> anyhow::Result<Value> {
+    let application = get_application_by_id(id)?;
+    let revision = match revision {
+        Some(revision) => Some(get_revision(id, revision)?),
+        None => get_revisions(id)?.pop(),
+    };
+    if let Some(revision) = &revision {
+        if let Some(snapshot) = get_revision_values(id, revision.revision)? {
+            return Ok(snapshot.values);
+        }
+    }
+    let (compose_path, values) = match revision {
+        Some(revision) => (revision.compose_path, revision.values),
+        None => (application.compose_path, application.values),
+    };
+    values
+        .with_default_values(Path::new(&compose_path))
+        .load_without_environment()
+        .with_context(|| format!("Could not load the values of application {}", id))
+}
+
+/// Returns every rendered compose file of an application, or one of its revisions, each preceded
+/// by a `# Source:` comment with its path relative to the application and separated by `---`.
+fn get_manifest(id: &str, revision: Option<u32>) -> anyhow::Result<String> {
+    // Fail early if the application is not installed
+    get_application_by_id(id)?;
+    let directory: PathBuf = match revision {
+        Some(revision) => {
+            get_revision(id, revision)?;
+            get_revision_files_directory(id, revision)?
+        }
+        None => get_composer_directory()?.join(id),
+    };
+    let mut compose_files =
+        get_files_with_name(&directory.to_string_lossy(), "docker-compose.jinja2");
+    if compose_files.is_empty() {
+        return Err(anyhow!(
+            "Could not find any compose files for application {} in {}",
+            id,
+            directory.display()
+        ));
+    }
+    compose_files.sort();
+    let mut manifests = Vec::new();
+    for compose_file in compose_files {
+        let relative_path = Path::new(&compose_file)
+            .strip_prefix(&directory)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| compose_file.clone());
+        let contents = fs::read_to_string(&compose_file)
+            .with_context(|| format!("Could not read {}", compose_file))?;
+        manifests.push(format!(
+            "# Source: {}\n{}",
+            relative_path,
+            contents.trim_end()
+        ));
+    }
+    Ok(manifests.join("\n---\n"))
+}
+
+impl Get {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        match &self.cmd {
+            GetCmd::Values(get_values_cmd) => {
+                let values = get_values(&get_values_cmd.id, get_values_cmd.revision)?;
+                print!("{}", serde_yaml::to_string(&values)?);
+            }
+            GetCmd::Manifest(get_manifest_cmd) => {
+                println!(
+                    "{}",
+                    get_manifest(&get_manifest_cmd.id, get_manifest_cmd.revision)?
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::install::Install;
+    use crate::utils::load_values::ValueOptions;
+    use crate::utils::storage::revisions::get_history_directory;
+    use crate::utils::test_utils::clean_up_test_folder;
+    use relative_path::RelativePath;
+    use serial_test::serial;
+    use std::env::current_dir;
+
+    #[test]
+    #[serial]
+    fn test_get_application_not_found() -> anyhow::Result<()> {
+        trace!("Running test_get_application_not_found.");
+        let id = "test_get_application_not_found";
+        assert!(get_values(id, None).is_err());
+        assert!(get_manifest(id, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_values_and_manifest() -> anyhow::Result<()> {
+        trace!("Running test_get_values_and_manifest.");
+        let id = "test_get_values_and_manifest";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions {
+                value_files: vec![values_str],
+                set: vec!["world=from-set".to_string()],
+                ..Default::default()
+            },
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        let values = get_values(id, None);
+        let revision_values = get_values(id, Some(1));
+        let manifest = get_manifest(id, None);
+        let revision_manifest = get_manifest(id, Some(1));
+        let missing_revision = get_manifest(id, Some(2));
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        let values = values?;
+        assert_eq!(values["world"], Value::String("from-set".to_string()));
+        assert_eq!(values["foo"]["bar"], Value::String("hi".to_string()));
+        assert_eq!(revision_values?, values);
+        let manifest = manifest?;
+        assert!(manifest.starts_with("# Source: docker-compose.jinja2\n"));
+        assert!(manifest.contains("\"from-set\""));
+        assert_eq!(revision_manifest?, manifest);
+        assert!(missing_revision.is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_values_without_snapshot_ignores_environment() -> anyhow::Result<()> {
+        trace!("Running test_get_values_without_snapshot_ignores_environment.");
+        let id = "test_get_values_without_snapshot_ignores_environment";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        // Revisions recorded before values were snapshotted have no values.yaml
+        fs::remove_file(get_history_directory(id)?.join("1").join("values.yaml"))?;
+        std::env::set_var("COMPOSER_VALUE_get_values_secret", "secret");
+        let values = get_values(id, None);
+        std::env::remove_var("COMPOSER_VALUE_get_values_secret");
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        let values = values?;
+        assert_eq!(values["foo"]["bar"], Value::String("hi".to_string()));
+        assert!(values.get("get_values_secret").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use crate::commands::cli::OutputFormat;
+use crate::commands::list::humanize_elapsed;
+use crate::utils::storage::models::Revision;
+use crate::utils::storage::read_from::get_application_by_id;
+use crate::utils::storage::revisions::get_revisions;
+use clap::Args;
+
+// Formats the row of every revision, only the revision numbers if quiet and with the value
+// files if wide
+fn format_revisions(revisions: &[Revision], quiet: bool, wide: bool) -> Vec<String> {
+    revisions
+        .iter()
+        .map(|revision| {
+            if quiet {
+                return revision.revision.to_string();
+            }
+            let time_formatted = humanize_elapsed(revision.timestamp);
+            if !wide {
+                format!(
+                    "{revision:<10} {version:<15} {time:<15} {status:<15} {description:<25}",
+                    revision = revision.revision,
+                    version = revision.version,
+                    time = time_formatted,
+                    status = revision.state,
+                    description = revision.description
+                )
+            } else {
+                format!(
+                    "{revision:<10} {version:<15} {time:<15} {status:<15} {description:<25} {value_files}",
+                    revision = revision.revision,
+                    version = revision.version,
+                    time = time_formatted,
+                    status = revision.state,
+                    description = revision.description,
+                    value_files = revision.values.value_files.join(",")
+                )
+            }
+        })
+        .collect()
+}
+
+fn print_revisions(revisions: &[Revision], quiet: bool, wide: bool) {
+    for row in format_revisions(revisions, quiet, wide) {
+        if quiet {
+            println!("{}", row);
+        } else {
+            info_no_bold!("{}", row);
+        }
+    }
+}
+
+// Every revision as a pretty printed JSON array
+fn format_revisions_json(revisions: &[Revision]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(revisions)?)
+}
+
+#[derive(Debug, Args)]
+pub struct History {
+    /// The id of the application to show the revision history of
+    #[clap(index = 1)]
+    pub id: String,
+    /// Prints only the revision numbers
+    #[clap(short, long)]
+    pub quiet: bool,
+    /// A more detailed output for each revision, including the value files used
+    #[clap(short, long)]
+    pub wide: bool,
+    /// The output format, json prints every revision as a machine readable array
+    #[clap(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+impl History {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        // Fail early if the application is not installed
+        get_application_by_id(&self.id)?;
+        let revisions = get_revisions(&self.id)?;
+        if self.output == OutputFormat::Json {
+            println!("{}", format_revisions_json(&revisions)?);
+            return Ok(());
+        }
+        if revisions.is_empty() {
+            info!("No revision history recorded for application {}", self.id);
+            return Ok(());
+        }
+        if !self.quiet && !self.wide {
+            info!(
+                "{revision:<10} {version:<15} {time:<15} {status:<15} {description:<25}",
+                revision = "REVISION",
+                version = "VERSION",
+                time = "UPDATED",
+                status = "STATUS",
+                description = "DESCRIPTION"
+            );
+        } else if !self.quiet {
+            info!(
+                "{revision:<10} {version:<15} {time:<15} {status:<15} {description:<25} {value_files}",
+                revision = "REVISION",
+                version = "VERSION",
+                time = "UPDATED",
+                status = "STATUS",
+                description = "DESCRIPTION",
+                value_files = "VALUE FILES"
+            );
+        }
+        print_revisions(&revisions, self.quiet, self.wide);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::load_values::ValueOptions;
+    use super::*;
+    use crate::commands::install::Install;
+    use crate::commands::upgrade::Upgrade;
+    use crate::utils::test_utils::clean_up_test_folder;
+    use relative_path::RelativePath;
+    use serial_test::serial;
+    use std::env::current_dir;
+
+    #[test]
+    #[serial]
+    fn test_history_application_not_found() -> anyhow::Result<()> {
+        trace!("Running test_history_application_not_found.");
+        let history_cmd = History {
+            id: "test_history_application_not_found".to_string(),
+            quiet: false,
+            wide: false,
+            output: OutputFormat::Table,
+        };
+        assert!(history_cmd.exec().is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_history_after_upgrade() -> anyhow::Result<()> {
+        trace!("Running test_history_after_upgrade.");
+        let id = "test_history_after_upgrade";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let override_str = RelativePath::new("resources/test/test_values/override.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        clean_up_test_folder(id)?;
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        Upgrade {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str.clone(), override_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
+        }
+        .exec()?;
+        let result = History {
+            id: id.to_string(),
+            quiet: false,
+            wide: true,
+            output: OutputFormat::Json,
+        }
+        .exec();
+        let revisions = get_revisions(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert!(result.is_ok());
+
+        let time = |revision: &Revision| humanize_elapsed(revision.timestamp);
+        assert_eq!(
+            format_revisions(&revisions, false, false),
+            vec![
+                format!(
+                    "1          1.0.0           {:<15} RUNNING         Install complete         ",
+                    time(&revisions[0])
+                ),
+                format!(
+                    "2          1.0.0           {:<15} RUNNING         Upgrade complete         ",
+                    time(&revisions[1])
+                ),
+            ]
+        );
+        let wide_rows = format_revisions(&revisions, false, true);
+        assert!(wide_rows[0].ends_with(&format!("Install complete          {}", values_str)));
+        assert!(wide_rows[1].ends_with(&format!(
+            "Upgrade complete          {},{}",
+            values_str, override_str
+        )));
+        assert_eq!(format_revisions(&revisions, true, true), vec!["1", "2"]);
+
+        let json: serde_json::Value = serde_json::from_str(&format_revisions_json(&revisions)?)?;
+        let json_revisions = json.as_array().unwrap();
+        assert_eq!(json_revisions.len(), 2);
+        assert_eq!(json_revisions[1]["revision"], 2);
+        assert_eq!(json_revisions[1]["version"], "1.0.0");
+        assert_eq!(json_revisions[1]["state"], "RUNNING");
+        assert_eq!(json_revisions[1]["description"], "Upgrade complete");
+        assert_eq!(
+            json_revisions[1]["value_files"],
+            serde_json::json!([values_str, override_str])
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_history_after_install() -> anyhow::Result<()> {
+        trace!("Running test_history_after_install.");
+        let id = "test_history_after_install";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        let result = History {
+            id: id.to_string(),
+            quiet: false,
+            wide: true,
+            output: OutputFormat::Json,
+        }
+        .exec();
+        let revisions = get_revisions(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert!(result.is_ok());
+
+        assert_eq!(
+            format_revisions(&revisions, false, true),
+            vec![format!(
+                "1          1.0.0           {:<15} RUNNING         Install complete          {}",
+                humanize_elapsed(revisions[0].timestamp),
+                values_str
+            )]
+        );
+        let json: serde_json::Value = serde_json::from_str(&format_revisions_json(&revisions)?)?;
+        assert_eq!(json.as_array().unwrap().len(), 1);
+        assert_eq!(json[0]["revision"], 1);
+        assert_eq!(json[0]["version"], "1.0.0");
+        assert_eq!(json[0]["state"], "RUNNING");
+        assert_eq!(json[0]["description"], "Install complete");
+        assert_eq!(json[0]["value_files"], serde_json::json!([values_str]));
+        Ok(())
+    }
+}
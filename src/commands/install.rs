@@ -1,20 +1,32 @@
 use crate::app;
 use crate::utils::copy_file_utils::{copy_files_with_ignorefile, get_composer_directory};
-use crate::utils::load_values::{get_value_files_as_refs, load_yaml_files};
+use crate::utils::load_values::{ReusedValues, ValueLayers, ValueOptions};
 use crate::utils::walk::{get_files_with_extension, get_files_with_name};
 use anyhow::anyhow;
 
-use crate::utils::docker_compose::{compose_pull, compose_up};
+use crate::utils::docker_compose::{
+    check_compose_is_valid, compose_down, compose_pull, compose_up,
+};
 use crate::utils::storage::app_yaml::load_app_yaml;
 use crate::utils::storage::models::{ApplicationState, PersistedApplication};
+use crate::utils::storage::read_from::get_application_by_id;
+use crate::utils::storage::revisions::{
+    delete_revision, record_revision, update_revision, ValuesSnapshot,
+};
 use crate::utils::storage::write_to_storage::append_to_storage;
-use crate::utils::template::render_template;
+use crate::utils::template::render_template_with_cwd;
+use crate::utils::values_schema::validate_values;
+use crate::utils::wait::{wait_for_application, WaitOptions};
 use clap::Args;
+use serde_yaml::Value;
 
 use std::fs;
 use std::fs::{remove_file, write};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(crate) const STAGING_RENDERED_DIRECTORY: &str = "rendered";
+pub(crate) const STAGING_PREVIOUS_DIRECTORY: &str = "previous";
 
 #[derive(Debug, Args)]
 pub struct Install {
@@ -22,13 +34,32 @@ pub struct Install {
     pub directory: PathBuf,
     #[clap(short, long)]
     pub id: Option<String>,
-    #[clap(short, long)]
-    pub value_files: Vec<String>,
+    #[clap(flatten)]
+    pub values: ValueOptions,
+    /// Render and validate the application without installing it, ~/.composer and docker are
+    ///   left untouched
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Used with --dry-run, writes the rendered application to this directory instead of
+    ///   printing the rendered templates
+    #[clap(long, requires = "dry_run")]
+    pub output_dir: Option<PathBuf>,
+    #[clap(flatten)]
+    pub wait: WaitOptions,
 }
 
 impl Install {
     pub fn exec(&self) -> anyhow::Result<()> {
         trace!("Command: {:?}", self);
+        if self.dry_run {
+            return dry_run_application(
+                false,
+                &self.values,
+                None,
+                &self.directory,
+                self.output_dir.as_deref(),
+            );
+        }
         let readable_id = &Self::get_readable_id();
         let install_id: &String = self.id.as_ref().unwrap_or(readable_id);
         // Ensure the .composer directory exists
@@ -44,8 +75,10 @@ impl Install {
             install_id,
             &composer_id_directory,
             false,
-            &self.value_files,
+            &self.values,
+            None,
             &self.directory,
+            &self.wait,
         )?;
 
         Ok(())
@@ -56,20 +89,20 @@ impl Install {
     }
 }
 
-fn get_current_timestamp() -> i64 {
+pub(crate) fn get_current_timestamp() -> i64 {
     let now = SystemTime::now();
     let duration_since_epoch = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
 
     duration_since_epoch.as_secs() as i64
 }
 
-fn verify_required_files(directory: &PathBuf) -> anyhow::Result<()> {
+fn verify_required_files(directory: &Path) -> anyhow::Result<()> {
     verify_file_exists("app.yaml", directory)?;
     verify_file_exists("docker-compose.jinja2", directory)?;
     Ok(())
 }
 
-fn verify_file_exists(file_name: &str, directory: &PathBuf) -> anyhow::Result<()> {
+fn verify_file_exists(file_name: &str, directory: &Path) -> anyhow::Result<()> {
     let file_path = directory.join(file_name);
     if !file_path.exists() {
         return Err(anyhow!(format!(
@@ -81,14 +114,15 @@ fn verify_file_exists(file_name: &str, directory: &PathBuf) -> anyhow::Result<()
     Ok(())
 }
 
-pub fn add_application(
-    install_id: &String,
-    composer_id_directory: &PathBuf,
+// Loads the template's default values and the given values into a single consolidated set of
+// values to render the application with, alongside the layers they were loaded from
+fn load_application_values(
+    directory: &Path,
+    values: &ValueOptions,
+    reused: Option<&ReusedValues>,
     is_upgrade: bool,
-    values_files: &Vec<String>,
-    directory: &PathBuf,
-) -> anyhow::Result<()> {
-    if values_files.is_empty() {
+) -> anyhow::Result<(Value, ValueLayers)> {
+    if reused.is_none() && values.with_default_values(directory).is_empty() {
         let mut correct_word = "install";
         if is_upgrade {
             correct_word = "upgrade";
@@ -98,36 +132,72 @@ pub fn add_application(
             ));
     }
 
-    let values = get_value_files_as_refs(values_files);
-    let consolidated_values = load_yaml_files(&values)?;
+    let layers = values.read_with_default_values(directory, reused)?;
+    let consolidated_values = layers.values()?;
     trace!(
         "Consolidated values: \n```\n{}\n```\n",
         serde_yaml::to_string(&consolidated_values).unwrap()
     );
+    Ok((consolidated_values, layers))
+}
 
-    if !directory.exists() {
-        return Err(anyhow!(format!(
-            "Template directory {} does not exist.",
-            &directory.display()
-        )));
-    }
-    // Check for app.yaml and docker-compose.jinja2
-    verify_required_files(directory)?;
-    // Check if there is an ignore file
-    let mut ignore_file_optional: Option<&Path> = None;
-    let composer_ignore_path = directory.join(".composerignore");
-    if composer_id_directory.exists() {
-        ignore_file_optional = Some(composer_ignore_path.as_path());
-    }
-    // Create the directory to copy the files to
-    fs::create_dir_all(&composer_id_directory)?;
-
-    // Copy the files to the .composer directory  using the ID as the folder name
-    copy_files_with_ignorefile(directory, &composer_id_directory, ignore_file_optional)?;
+/// Renders, stores and starts an application, recording it as a new revision.
+///
+/// `values` are the values the application is rendered with, they are stored in config.json and
+/// snapshotted with the revision. An upgrade that reuses the values of the previous revision
+/// renders `values` layered over them, see `ReusedValues`. If asked to wait, the application is only marked as RUNNING once
+/// its services are ready, otherwise it is marked as ERROR and the failed revision is recorded.
+/// The revision is recorded from the staged files and only updated once the application is up.
+///
+/// The application is rendered into a staging directory first and only swapped into
+/// `composer_id_directory` once rendering has succeeded, so a failed upgrade never leaves an
+/// application without its files. If compose up fails during an upgrade the previous files are
+/// restored and brought back up.
+pub fn add_application(
+    install_id: &String,
+    composer_id_directory: &PathBuf,
+    is_upgrade: bool,
+    values: &ValueOptions,
+    reused: Option<&ReusedValues>,
+    directory: &PathBuf,
+    wait: &WaitOptions,
+) -> anyhow::Result<()> {
+    let (consolidated_values, layers) =
+        load_application_values(directory, values, reused, is_upgrade)?;
+    // Snapshot the values without any environment values, as they are often secrets
+    let values_snapshot = ValuesSnapshot {
+        values: layers.values_without_environment()?,
+        user_values: layers.user_values()?,
+    };
 
+    // Render into the staging directory, removing anything left over by an interrupted upgrade
+    let staging_directory = get_staging_directory(install_id)?;
+    let rendered_directory = staging_directory.join(STAGING_RENDERED_DIRECTORY);
+    let previous_directory = staging_directory.join(STAGING_PREVIOUS_DIRECTORY);
+    if staging_directory.exists() {
+        fs::remove_dir_all(&staging_directory)?;
+    }
+    // composer.cwd is rendered as the final directory, not the staging directory
+    let rendered = render_application(
+        directory,
+        &rendered_directory,
+        composer_id_directory,
+        &consolidated_values,
+    )
+    .and_then(|_| load_app_yaml(directory.join("app.yaml")));
     // Read App.yaml to get some of the needed values
-    let app_yaml_path = directory.join("app.yaml");
-    let app_yaml = load_app_yaml(app_yaml_path)?;
+    let app_yaml = match rendered {
+        Ok(app_yaml) => app_yaml,
+        Err(e) => {
+            fs::remove_dir_all(&staging_directory).ok();
+            return Err(e);
+        }
+    };
+    let previous_application = if is_upgrade {
+        Some(get_application_by_id(install_id)?)
+    } else {
+        None
+    };
     // Create the persisted application struct
     let mut application = PersistedApplication {
         id: install_id.to_string(),
@@ -139,25 +209,262 @@ pub fn add_application(
             .unwrap_or_else(|_| directory.clone())
             .to_string_lossy()
             .to_string(),
-        value_files: values_files.clone(),
+        values: match reused {
+            Some(reused) => reused.stored_with(values),
+            None => values.clone(),
+        }
+        .to_stored(),
+    };
+    // Record the revision from the staged files, before any runtime data can be written next to them
+    let (started, complete, failed) = if is_upgrade {
+        ("Upgrade started", "Upgrade complete", "Upgrade failed")
+    } else {
+        ("Install started", "Install complete", "Install failed")
+    };
+    let revision = match record_revision(
+        &application,
+        &rendered_directory,
+        Some(&values_snapshot),
+        started,
+    ) {
+        Ok(revision) => revision.revision,
+        Err(e) => {
+            fs::remove_dir_all(&staging_directory).ok();
+            return Err(e);
+        }
     };
+    // Swap the rendered files in, keeping the previous files until the application is up
+    if composer_id_directory.exists() {
+        fs::rename(composer_id_directory, &previous_directory)?;
+    }
+    fs::rename(&rendered_directory, composer_id_directory)?;
     // Change status of app to starting
     append_to_storage(&application)?;
+
+    if let Err(e) = compose_up_application(install_id, composer_id_directory) {
+        // The application never came up, so the revision is not kept in its history
+        delete_revision(install_id, revision)?;
+        if let Some(previous_application) = previous_application {
+            restore_previous_application(
+                &previous_application,
+                composer_id_directory,
+                &previous_directory,
+            )?;
+            fs::remove_dir_all(&staging_directory)?;
+            return Err(e.context(format!(
+                "Upgrade of application {} has failed and the previous files have been restored",
+                install_id
+            )));
+        }
+        fs::remove_dir_all(&staging_directory)?;
+        return Err(e);
+    }
+    fs::remove_dir_all(&staging_directory)?;
+
+    if wait.wait && !app::no_run() {
+        if let Err(e) = wait_for_application(install_id, Duration::from_secs(wait.timeout)) {
+            application.state = ApplicationState::ERROR;
+            append_to_storage(&application)?;
+            update_revision(install_id, revision, ApplicationState::ERROR, failed)?;
+            return Err(e);
+        }
+    }
+
+    // Change status of app to running
+    if !app::no_run() {
+        application.state = ApplicationState::RUNNING;
+    }
+    append_to_storage(&application)?;
+    update_revision(install_id, revision, application.state.clone(), complete)?;
+    Ok(())
+}
+
+/// Returns the directory an application is rendered into before it is swapped into place, i.e.
+/// `~/.composer/.staging/<id>`.
+pub(crate) fn get_staging_directory(id: &str) -> anyhow::Result<PathBuf> {
+    Ok(get_composer_directory()?.join(".staging").join(id))
+}
+
+// Brings down compose files that only exist in the failed upgrade or rollback, then swaps the
+// previous files and stored application back in and brings them up again
+pub(crate) fn restore_previous_application(
+    previous_application: &PersistedApplication,
+    composer_id_directory: &Path,
+    previous_directory: &Path,
+) -> anyhow::Result<()> {
+    warn!(
+        "Application {} has failed to start, restoring the previous files.",
+        previous_application.id
+    );
+    let previous_compose_files = get_relative_compose_files(previous_directory);
+    for compose_file in get_relative_compose_files(composer_id_directory) {
+        if !previous_compose_files.contains(&compose_file) {
+            let compose_path = composer_id_directory.join(&compose_file);
+            compose_down(compose_path.to_str().unwrap(), &previous_application.id)?;
+        }
+    }
+    fs::remove_dir_all(composer_id_directory)?;
+    fs::rename(previous_directory, composer_id_directory)?;
+    append_to_storage(previous_application)?;
+    compose_up_application(&previous_application.id, composer_id_directory)
+}
+
+/// Returns the docker-compose.jinja2 files under a directory relative to it
+pub(crate) fn get_relative_compose_files(directory: &Path) -> Vec<PathBuf> {
+    get_files_with_name(directory.to_str().unwrap(), "docker-compose.jinja2")
+        .into_iter()
+        .filter_map(|file| {
+            Path::new(&file)
+                .strip_prefix(directory)
+                .ok()
+                .map(|relative| relative.to_path_buf())
+        })
+        .collect()
+}
+
+/// Renders and validates an application exactly as an install or upgrade would, without touching
+/// `~/.composer`, config.json or docker.
+///
+/// The rendered templates are printed to stdout, or if `output_directory` is given the whole
+/// rendered application is written there instead.
+pub fn dry_run_application(
+    is_upgrade: bool,
+    values: &ValueOptions,
+    reused: Option<&ReusedValues>,
+    directory: &Path,
+    output_directory: Option<&Path>,
+) -> anyhow::Result<()> {
+    let (consolidated_values, _) = load_application_values(directory, values, reused, is_upgrade)?;
+    // Render into a temporary directory unless asked to keep the output
+    let temporary_directory = tempfile::tempdir()?;
+    let destination = output_directory.unwrap_or(temporary_directory.path());
+    render_application(directory, destination, destination, &consolidated_values)?;
+    let app_yaml = load_app_yaml(directory.join("app.yaml"))?;
+
+    let compose_files =
+        get_files_with_name(destination.to_str().unwrap(), "docker-compose.jinja2");
+    for compose_file in &compose_files {
+        check_compose_is_valid(compose_file)?;
+    }
+
+    if output_directory.is_none() {
+        let templates = get_files_with_extension(directory.to_str().unwrap(), "jinja2");
+        for template in templates {
+            let relative_path = Path::new(&template).strip_prefix(directory)?;
+            let rendered_path = destination.join(relative_path);
+            // Templates excluded by .composerignore are not rendered
+            if let Ok(rendered) = fs::read_to_string(&rendered_path) {
+                println!("---\n# Source: {}\n{}", relative_path.display(), rendered);
+            }
+        }
+    } else {
+        info!("Rendered application written to {}", destination.display());
+    }
+    success!(
+        "Dry run of {} {} complete, {} compose file(s) are valid. Nothing has been installed.",
+        app_yaml.name,
+        app_yaml.version,
+        compose_files.len()
+    );
+    Ok(())
+}
+
+/// Copies a template directory into `destination`, skipping anything matched by its
+/// `.composerignore`, then renders every `.jinja2` file in place with the given values.
+///
+/// # Arguments
+///
+/// * `directory` - The template directory containing app.yaml and docker-compose.jinja2.
+/// * `destination` - The directory to render the application into, created if it doesn't exist.
+/// * `application_directory` - The directory the application is run from, `composer.cwd` is
+///   rendered relative to it rather than to `destination`, e.g. `~/.composer/<id>`.
+/// * `consolidated_values` - The values to render the templates with.
+pub fn render_application(
+    directory: &Path,
+    destination: &Path,
+    application_directory: &Path,
+    consolidated_values: &Value,
+) -> anyhow::Result<()> {
+    if !directory.exists() {
+        return Err(anyhow!(format!(
+            "Template directory {} does not exist.",
+            &directory.display()
+        )));
+    }
+    // Check for app.yaml and docker-compose.jinja2
+    verify_required_files(directory)?;
+    let strict = *app::strict() || load_app_yaml(directory.join("app.yaml"))?.strict;
+    // Check the values against the template's values schema before anything is copied
+    validate_values(directory, consolidated_values)?;
+    copy_application(directory, destination)?;
+
     // For each template render them, then replace them with the actual file
     // Replace the jinja files with templated ones
-    let files_to_replace =
-        get_files_with_extension(composer_id_directory.to_str().unwrap(), "jinja2");
+    let files_to_replace = get_files_with_extension(destination.to_str().unwrap(), "jinja2");
     trace!("Detected templates: {}", files_to_replace.join(","));
 
     for file_path in files_to_replace {
-        trace!("Replacing {}", file_path);
-        // Get the rendered template
-        let rendered_content = render_template(&file_path, consolidated_values.clone())?;
-        // Replace the existing file
-        remove_file(&file_path)?;
-        write(file_path, rendered_content.as_bytes())?;
+        render_application_template(
+            &file_path,
+            destination,
+            application_directory,
+            consolidated_values,
+            strict,
+        )?;
+    }
+    Ok(())
+}
+
+/// Copies a template directory into `destination`, skipping anything matched by its
+/// `.composerignore`.
+pub fn copy_application(directory: &Path, destination: &Path) -> anyhow::Result<()> {
+    // Check if there is an ignore file
+    let mut ignore_file_optional: Option<&Path> = None;
+    let composer_ignore_path = directory.join(".composerignore");
+    if composer_ignore_path.exists() {
+        ignore_file_optional = Some(composer_ignore_path.as_path());
     }
+    // Create the directory to copy the files to
+    fs::create_dir_all(destination)?;
+    copy_files_with_ignorefile(directory, destination, ignore_file_optional)
+}
 
+/// Renders a template copied into `destination` and replaces it with the rendered content, with
+/// `composer.cwd` rendered relative to `application_directory`.
+///
+/// # Returns
+///
+/// The rendered content of the template.
+pub fn render_application_template(
+    file_path: &str,
+    destination: &Path,
+    application_directory: &Path,
+    consolidated_values: &Value,
+    strict: bool,
+) -> anyhow::Result<String> {
+    trace!("Replacing {}", file_path);
+    // The directory the file will be in once the application is in place
+    let cwd = Path::new(file_path)
+        .parent()
+        .and_then(|parent| parent.strip_prefix(destination).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(|relative| application_directory.join(relative))
+        .unwrap_or_else(|| application_directory.to_path_buf());
+    // Get the rendered template
+    let rendered_content =
+        render_template_with_cwd(file_path, &cwd, consolidated_values.clone(), strict)?;
+    // Replace the existing file
+    remove_file(file_path)?;
+    write(file_path, rendered_content.as_bytes())?;
+    Ok(rendered_content)
+}
+
+/// Pulls (if always pull is enabled) and brings up every docker-compose.jinja2 file found
+/// in the rendered application directory.
+pub fn compose_up_application(
+    install_id: &str,
+    composer_id_directory: &Path,
+) -> anyhow::Result<()> {
     let no_run = app::no_run();
 
     // Find all docker-compose.jinja2 files
@@ -170,31 +477,29 @@ pub fn add_application(
             info!("Always pull is enabled. Pulling latest docker images.");
             let compose_path = composer_id_directory.join(&compose_file);
             let dir_str = compose_path.to_str().unwrap();
-            compose_pull(dir_str);
+            compose_pull(dir_str)?;
         }
         if !no_run {
             compose_up(&compose_file, install_id)?;
         }
     }
-
-    // Change status of app to running
-    if !no_run {
-        application.state = ApplicationState::RUNNING;
-    }
-    append_to_storage(&application)?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::utils::load_values::ValueOptions;
     use relative_path::RelativePath;
 
     use crate::commands::install::{verify_file_exists, Install};
+    use crate::utils::copy_file_utils::get_composer_directory;
+    use crate::utils::docker_compose::ComposeError;
 
     use crate::utils::storage::models::ApplicationState;
     use crate::utils::storage::read_from::get_application_by_id;
     use serial_test::serial;
     use std::env::current_dir;
+    use std::fs;
     use std::path::PathBuf;
     use crate::utils::test_utils::clean_up_test_folder;
 
@@ -208,7 +513,10 @@ mod tests {
         let test_install_cmd = Install {
             directory: install_dir,
             id: Some(id.to_string()),
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         let err = test_install_cmd.exec().unwrap_err();
         let actual_err = err.to_string();
@@ -228,7 +536,10 @@ mod tests {
         let test_install_cmd = Install {
             directory: install_dir,
             id: Some(id.to_string()),
-            value_files: vec![String::from("doesNotExist.yaml")],
+            values: ValueOptions::from_value_files(vec![String::from("doesNotExist.yaml")]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         let err = test_install_cmd.exec().unwrap_err();
         let actual_err = err.to_string();
@@ -253,7 +564,10 @@ mod tests {
         let test_install_cmd = Install {
             directory: install_dir,
             id: Some(id.to_string()),
-            value_files: vec![values_str],
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         let err = test_install_cmd.exec().unwrap_err();
         let actual_err = err.to_string();
@@ -275,7 +589,10 @@ mod tests {
         let test_install_cmd = Install {
             directory: PathBuf::from("does_not_exist"),
             id: Some(id.to_string()),
-            value_files: vec![values_str],
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         let err = test_install_cmd.exec().unwrap_err();
         let actual_err = err.to_string();
@@ -298,7 +615,10 @@ mod tests {
         let test_install_cmd = Install {
             directory: install_dir,
             id: Some(id.to_string()),
-            value_files: vec![values_str],
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         // Call exec once, so that the folder is created
         test_install_cmd.exec()?;
@@ -325,7 +645,10 @@ mod tests {
         let test_install_cmd = Install {
             directory: install_dir.clone(),
             id: Some(id.to_string()),
-            value_files: vec![values_str],
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         test_install_cmd.exec()?;
 
@@ -347,7 +670,10 @@ mod tests {
         let install = Install {
             directory: PathBuf::from("resources/test/simple/"),
             id: None,
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         let result = verify_file_exists("app.yaml", &install.directory);
         assert!(result.is_ok());
@@ -359,7 +685,10 @@ mod tests {
         let install = Install {
             directory: PathBuf::from("resources/test/simple/"),
             id: None,
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
 
         let result = verify_file_exists("non_existent_file.txt", &install.directory);
@@ -384,14 +713,207 @@ mod tests {
         let test_install_cmd = Install {
             directory: install_dir.clone(),
             id: Some(id.to_string()),
-            value_files: value_files.clone(),
+            values: ValueOptions::from_value_files(value_files.clone()),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
         };
         test_install_cmd.exec()?;
         // Read the created app
         let app = get_application_by_id(id)?;
         // Clean up the app after test
         clean_up_test_folder(id)?;
-        assert_eq!(app.value_files, value_files);
+        assert_eq!(app.values.value_files, value_files);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_dry_run_install_does_not_install() -> anyhow::Result<()> {
+        trace!("Running test_dry_run_install_does_not_install.");
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/")
+            .to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let id = "test_dry_run_install_does_not_install";
+        let output_dir = tempfile::tempdir()?;
+        let test_install_cmd = Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: true,
+            output_dir: Some(output_dir.path().to_path_buf()),
+            wait: Default::default(),
+        };
+        test_install_cmd.exec()?;
+        let rendered = output_dir.path().join("docker-compose.jinja2").exists();
+        let installed_directory = get_composer_directory()?.join(id).exists();
+        let installed_application = get_application_by_id(id).is_ok();
+        assert!(rendered);
+        assert!(!installed_directory);
+        assert!(!installed_application);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_dry_run_install_invalid_compose() -> anyhow::Result<()> {
+        trace!("Running test_dry_run_install_invalid_compose.");
+        let current_dir = current_dir()?;
+        let install_dir =
+            RelativePath::new("resources/test/invalid_compose/").to_logical_path(&current_dir);
+        let test_install_cmd = Install {
+            directory: install_dir,
+            id: Some("test_dry_run_install_invalid_compose".to_string()),
+            values: ValueOptions::default(),
+            dry_run: true,
+            output_dir: None,
+            wait: Default::default(),
+        };
+        // A rendered compose file that isn't YAML is reported as an error rather than a panic
+        let err = test_install_cmd.exec().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ComposeError>(),
+            Some(ComposeError::InvalidYaml { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_failed_install_values_do_not_match_schema() -> anyhow::Result<()> {
+        trace!("Running test_failed_install_values_do_not_match_schema.");
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/schema/")
+            .to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let id = "test_failed_install_values_do_not_match_schema";
+        let test_install_cmd = Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        };
+        let err = test_install_cmd.exec().unwrap_err();
+        let installed_directory = get_composer_directory()?.join(id).exists();
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert!(err.to_string().contains("<root>: \"image\" is a required property"));
+        assert!(!installed_directory);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_install_with_default_values() -> anyhow::Result<()> {
+        trace!("Running test_install_with_default_values.");
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/defaults/")
+            .to_logical_path(&current_dir);
+        let id = "test_install_with_default_values";
+        // The template's values.yaml is layered under the given values
+        let test_install_cmd = Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions {
+                set: vec!["world=override".to_string()],
+                ..Default::default()
+            },
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        };
+        test_install_cmd.exec()?;
+        let rendered = fs::read_to_string(
+            get_composer_directory()?
+                .join(id)
+                .join("docker-compose.jinja2"),
+        )?;
+        let app = get_application_by_id(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert!(rendered.contains("hello override"));
+        // Only the user's values are stored, the defaults are always read from the template
+        assert!(app.values.value_files.is_empty());
+        assert_eq!(app.values.set, vec!["world=override".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_install_renders_cwd_as_application_directory() -> anyhow::Result<()> {
+        trace!("Running test_install_renders_cwd_as_application_directory.");
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/cwd/").to_logical_path(&current_dir);
+        let id = "test_install_renders_cwd";
+        let test_install_cmd = Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        };
+        test_install_cmd.exec()?;
+        let composer_id_directory = get_composer_directory()?.join(id);
+        let compose_file = fs::read_to_string(composer_id_directory.join("docker-compose.jinja2"))?;
+        let settings_file =
+            fs::read_to_string(composer_id_directory.join("config").join("settings.jinja2"))?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        // composer.cwd is where the files are installed, not where they were rendered
+        assert!(compose_file.contains(&format!(
+            "\"{}/config:/config\"",
+            composer_id_directory.display()
+        )));
+        assert_eq!(
+            settings_file.trim(),
+            format!(
+                "directory: \"{}\"",
+                composer_id_directory.join("config").display()
+            )
+        );
+        assert!(!compose_file.contains(".staging"));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_install_skips_ignored_files() -> anyhow::Result<()> {
+        trace!("Running test_install_skips_ignored_files.");
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let id = "test_install_skips_ignored_files";
+        let test_install_cmd = Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        };
+        test_install_cmd.exec()?;
+        let composer_id_directory = get_composer_directory()?.join(id);
+        let ignored_file_installed = composer_id_directory.join(".ignoreme").exists();
+        let compose_file_installed = composer_id_directory.join("docker-compose.jinja2").exists();
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        // .ignoreme is matched by the template's .composerignore
+        assert!(install_dir.join(".ignoreme").exists());
+        assert!(!ignored_file_installed);
+        assert!(compose_file_installed);
         Ok(())
     }
 }
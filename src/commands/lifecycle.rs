@@ -0,0 +1,202 @@
+use crate::utils::docker_compose::{compose_lifecycle, get_compose_files_by_id};
+use crate::utils::storage::models::ApplicationState;
+use crate::utils::storage::read_from::{get_all_from_storage, if_application_exists};
+use crate::utils::storage::update_storage::update_application_state;
+use anyhow::anyhow;
+use clap::Args;
+
+/// A docker compose lifecycle action that can be run over every compose file of an application
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LifecycleAction {
+    Stop,
+    Start,
+    Restart,
+}
+
+impl LifecycleAction {
+    fn command(&self) -> &'static str {
+        match self {
+            LifecycleAction::Stop => "stop",
+            LifecycleAction::Start => "start",
+            LifecycleAction::Restart => "restart",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            LifecycleAction::Stop => "Stopped",
+            LifecycleAction::Start => "Started",
+            LifecycleAction::Restart => "Restarted",
+        }
+    }
+
+    // The state the application is in once the action has succeeded
+    fn resulting_state(&self) -> ApplicationState {
+        match self {
+            LifecycleAction::Stop => ApplicationState::STOPPED,
+            LifecycleAction::Start | LifecycleAction::Restart => ApplicationState::RUNNING,
+        }
+    }
+}
+
+fn run_action_by_id(id: &str, action: LifecycleAction) -> anyhow::Result<()> {
+    let mut failed = false;
+    for compose_file in get_compose_files_by_id(id)? {
+        if let Err(e) = compose_lifecycle(&compose_file, action.command()) {
+            error!("{:#}", e);
+            failed = true;
+        }
+    }
+    if failed {
+        update_application_state(id, ApplicationState::ERROR)?;
+        return Err(anyhow!(
+            "docker compose {} has failed for app {}",
+            action.command(),
+            id
+        ));
+    }
+    update_application_state(id, action.resulting_state())?;
+    info!("{} application {}", action.past_tense(), id);
+    Ok(())
+}
+
+fn run_action(ids: &[String], all: bool, action: LifecycleAction) -> anyhow::Result<()> {
+    // If the all flag is set, run the action on all applications
+    if all {
+        for app in get_all_from_storage()? {
+            run_action_by_id(&app.id, action)?;
+        }
+        return Ok(());
+    }
+    for id in ids {
+        if !if_application_exists(id) {
+            return Err(anyhow!(
+                "Could not find application '{}' to {} it.",
+                id,
+                action.command()
+            ));
+        }
+        run_action_by_id(id, action)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Args)]
+pub struct Stop {
+    /// The application ids to stop, space seperated to stop multiple applications at once
+    #[clap(index = 1, required_unless_present = "all", conflicts_with("all"))]
+    pub ids: Vec<String>,
+    /// If the all flag is set all composer applications will be stopped
+    #[clap(long)]
+    pub all: bool,
+}
+
+impl Stop {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        run_action(&self.ids, self.all, LifecycleAction::Stop)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Start {
+    /// The application ids to start, space seperated to start multiple applications at once
+    #[clap(index = 1, required_unless_present = "all", conflicts_with("all"))]
+    pub ids: Vec<String>,
+    /// If the all flag is set all composer applications will be started
+    #[clap(long)]
+    pub all: bool,
+}
+
+impl Start {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        run_action(&self.ids, self.all, LifecycleAction::Start)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Restart {
+    /// The application ids to restart, space seperated to restart multiple applications at once
+    #[clap(index = 1, required_unless_present = "all", conflicts_with("all"))]
+    pub ids: Vec<String>,
+    /// If the all flag is set all composer applications will be restarted
+    #[clap(long)]
+    pub all: bool,
+}
+
+impl Restart {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        run_action(&self.ids, self.all, LifecycleAction::Restart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::load_values::ValueOptions;
+    use super::*;
+    use crate::commands::install::Install;
+    use crate::utils::storage::read_from::get_application_by_id;
+    use crate::utils::test_utils::clean_up_test_folder;
+    use relative_path::RelativePath;
+    use serial_test::serial;
+    use std::env::current_dir;
+
+    #[test]
+    #[serial]
+    fn test_stop_application_not_found() -> anyhow::Result<()> {
+        trace!("Running test_stop_application_not_found.");
+        let stop_cmd = Stop {
+            ids: vec!["test_stop_application_not_found".to_string()],
+            all: false,
+        };
+        let err = stop_cmd.exec().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Could not find application 'test_stop_application_not_found' to stop it."
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_stop_then_start_updates_state() -> anyhow::Result<()> {
+        trace!("Running test_stop_then_start_updates_state.");
+        let id = "test_stop_then_start_updates_state";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+
+        Stop {
+            ids: vec![id.to_string()],
+            all: false,
+        }
+        .exec()?;
+        let stopped_state = get_application_by_id(id)?.state;
+        Start {
+            ids: vec![id.to_string()],
+            all: false,
+        }
+        .exec()?;
+        let started_state = get_application_by_id(id)?.state;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        assert_eq!(stopped_state, ApplicationState::STOPPED);
+        assert_eq!(started_state, ApplicationState::RUNNING);
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use crate::commands::install::{copy_application, render_application_template};
+use crate::utils::load_values::ValueOptions;
+use crate::utils::storage::app_yaml::load_app_yaml;
+use crate::utils::template::find_undefined_variables;
+use crate::utils::values_schema::{get_values_schema_path, validate_values};
+use crate::utils::walk::get_files_with_extension;
+use anyhow::anyhow;
+use clap::Args;
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Args)]
+pub struct Lint {
+    /// The template directory to lint
+    #[clap(index = 1)]
+    pub directory: PathBuf,
+    // The values to render the templates with, layered over values.yaml in the template directory
+    #[clap(flatten)]
+    pub values: ValueOptions,
+}
+
+/// A problem found while linting, reported against the file it was found in
+#[derive(Debug, PartialEq)]
+struct LintError {
+    file: String,
+    message: String,
+}
+
+impl LintError {
+    fn new(file: &str, message: String) -> Self {
+        LintError {
+            file: file.to_string(),
+            message,
+        }
+    }
+}
+
+fn lint_app_yaml(directory: &Path) -> Vec<LintError> {
+    let app_yaml_path = directory.join("app.yaml");
+    if !app_yaml_path.exists() {
+        return vec![LintError::new(
+            "app.yaml",
+            "Could not find app.yaml in the template directory".to_string(),
+        )];
+    }
+    let app_yaml = match load_app_yaml(&app_yaml_path) {
+        Ok(app_yaml) => app_yaml,
+        Err(e) => return vec![LintError::new("app.yaml", format!("{:#}", e))],
+    };
+    match semver::Version::parse(&app_yaml.version) {
+        Ok(_) => vec![],
+        Err(e) => vec![LintError::new(
+            "app.yaml",
+            format!(
+                "Version '{}' is not a valid semantic version: {}",
+                app_yaml.version, e
+            ),
+        )],
+    }
+}
+
+// Checks a rendered compose file is YAML with a services mapping
+fn lint_compose_file(file: &str, rendered: &str) -> Option<LintError> {
+    match serde_yaml::from_str::<Value>(rendered) {
+        Ok(compose) => match compose.get("services") {
+            Some(Value::Mapping(_)) => None,
+            _ => Some(LintError::new(
+                file,
+                "Rendered compose file does not contain a services mapping".to_string(),
+            )),
+        },
+        Err(e) => Some(LintError::new(
+            file,
+            format!("Rendered compose file is not valid YAML: {}", e),
+        )),
+    }
+}
+
+fn lint_templates(directory: &Path, values: &Value) -> anyhow::Result<Vec<LintError>> {
+    // Render a copy of the directory so the same files are linted as would be installed
+    let lint_directory = tempfile::tempdir()?;
+    copy_application(directory, lint_directory.path())?;
+
+    let mut templates = get_files_with_extension(lint_directory.path().to_str().unwrap(), "jinja2");
+    templates.sort();
+    let mut errors = Vec::new();
+    for template in templates {
+        let relative_path = Path::new(&template)
+            .strip_prefix(lint_directory.path())?
+            .to_string_lossy()
+            .to_string();
+        match find_undefined_variables(&template, values.clone()) {
+            Ok(undefined) => {
+                for variable in undefined {
+                    errors.push(LintError::new(
+                        &relative_path,
+                        format!("Variable '{}' is not defined in the values", variable),
+                    ));
+                }
+            }
+            Err(e) => {
+                errors.push(LintError::new(&relative_path, format!("{:#}", e)));
+                continue;
+            }
+        }
+        // Undefined variables are reported above, so render leniently to find any other errors
+        let rendered = match render_application_template(
+            &template,
+            lint_directory.path(),
+            lint_directory.path(),
+            values,
+            false,
+        ) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                errors.push(LintError::new(&relative_path, format!("{:#}", e)));
+                continue;
+            }
+        };
+        if Path::new(&template).file_name() == Some("docker-compose.jinja2".as_ref()) {
+            errors.extend(lint_compose_file(&relative_path, &rendered));
+        }
+    }
+    Ok(errors)
+}
+
+/// Lints a template directory without installing it, checking that:
+///
+/// * app.yaml exists, parses and has a semantic version.
+/// * docker-compose.jinja2 exists at the top level.
+/// * The values match the directory's values schema, if it has one.
+/// * Every `.jinja2` file renders and only references variables defined in the values.
+/// * Every rendered compose file is valid YAML with a `services` mapping.
+///
+/// The template directory's values.yaml is used as the lowest precedence layer of values.
+fn lint_directory(directory: &Path, values: &ValueOptions) -> anyhow::Result<Vec<LintError>> {
+    if !directory.is_dir() {
+        return Err(anyhow!(
+            "Template directory {} does not exist.",
+            directory.display()
+        ));
+    }
+    let mut errors = lint_app_yaml(directory);
+    if !directory.join("docker-compose.jinja2").exists() {
+        errors.push(LintError::new(
+            "docker-compose.jinja2",
+            "Could not find docker-compose.jinja2 in the template directory".to_string(),
+        ));
+    }
+
+    let values = values.with_default_values(directory);
+    if values.is_empty() {
+        errors.push(LintError::new(
+            "values",
+            "No value files to render the templates with, use -v <values path> to specify value files or add a values.yaml to the template directory.".to_string(),
+        ));
+        return Ok(errors);
+    }
+    let values = match values.load() {
+        Ok(values) => values,
+        Err(e) => {
+            errors.push(LintError::new("values", format!("{:#}", e)));
+            return Ok(errors);
+        }
+    };
+    if let Err(e) = validate_values(directory, &values) {
+        let schema_path = get_values_schema_path(directory).unwrap_or_default();
+        let schema_file = schema_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        errors.push(LintError::new(&schema_file, e.to_string()));
+    }
+    errors.extend(lint_templates(directory, &values)?);
+    Ok(errors)
+}
+
+impl Lint {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        let errors = lint_directory(&self.directory, &self.values)?;
+        if errors.is_empty() {
+            success!("{} passed lint", self.directory.display());
+            return Ok(());
+        }
+        for lint_error in &errors {
+            error!("{}: {}", lint_error.file, lint_error.message);
+        }
+        Err(anyhow!(
+            "Lint found {} error(s) in {}",
+            errors.len(),
+            self.directory.display()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use relative_path::RelativePath;
+    use std::env::current_dir;
+
+    #[test]
+    fn test_lint_valid_directory() -> anyhow::Result<()> {
+        trace!("Running test_lint_valid_directory.");
+        let current_dir = current_dir()?;
+        let directory = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let lint_cmd = Lint {
+            directory,
+            values: ValueOptions::from_value_files(vec![values_str]),
+        };
+        assert!(lint_cmd.exec().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_reports_every_error() -> anyhow::Result<()> {
+        trace!("Running test_lint_reports_every_error.");
+        let current_dir = current_dir()?;
+        let directory =
+            RelativePath::new("resources/test/lint_invalid/").to_logical_path(&current_dir);
+        let errors = lint_directory(&directory, &ValueOptions::default())?;
+        let reported: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|lint_error| (lint_error.file.as_str(), lint_error.message.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                (
+                    "app.yaml",
+                    "Version '1.0' is not a valid semantic version: unexpected end of input while parsing minor version number"
+                ),
+                (
+                    "docker-compose.jinja2",
+                    "Variable 'image.tag' is not defined in the values"
+                ),
+                (
+                    "docker-compose.jinja2",
+                    "Rendered compose file does not contain a services mapping"
+                ),
+            ]
+        );
+        let lint_cmd = Lint {
+            directory,
+            values: ValueOptions::default(),
+        };
+        let err = lint_cmd.exec().unwrap_err();
+        assert!(err.to_string().starts_with("Lint found 3 error(s)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_compose_file_invalid_yaml() {
+        let lint_error = lint_compose_file("docker-compose.jinja2", "services: [").unwrap();
+        assert!(lint_error
+            .message
+            .starts_with("Rendered compose file is not valid YAML"));
+    }
+}
@@ -1,28 +1,76 @@
-use crate::utils::storage::models::PersistedApplication;
+use crate::utils::docker_compose::{compose_ps_by_id, ContainerStatus};
+use crate::utils::storage::models::{ApplicationState, PersistedApplication};
 use crate::utils::storage::read_from::get_all_from_storage;
 use clap::Args;
 
 use chrono_humanize::HumanTime;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-fn print_applications(apps: &[PersistedApplication], quiet: bool, wide: bool) {
+/// Formats the time elapsed since a unix timestamp in seconds, e.g. "5 minutes"
+pub(crate) fn humanize_elapsed(timestamp: i64) -> String {
+    let time_delta = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs() as i64
+        - timestamp;
+    let duration = chrono::Duration::seconds(time_delta);
+
+    HumanTime::from(duration).to_text_en(
+        chrono_humanize::Accuracy::Rough,
+        chrono_humanize::Tense::Present,
+    )
+}
+
+/// Summarises the live state of an application's containers, e.g. "RUNNING 2/2" where the
+/// count is the number of running (and if they define a health check, healthy) containers.
+pub(crate) fn summarise_containers(containers: &[ContainerStatus]) -> String {
+    let total = containers.len();
+    if total == 0 {
+        return "NO CONTAINERS".to_string();
+    }
+    let running = containers.iter().filter(|c| c.state == "running").count();
+    let healthy = containers
+        .iter()
+        .filter(|c| c.state == "running" && (c.health.is_empty() || c.health == "healthy"))
+        .count();
+    let state = if containers.iter().any(|c| c.state == "restarting") {
+        "RESTARTING"
+    } else if running == 0 {
+        "EXITED"
+    } else if healthy == total {
+        "RUNNING"
+    } else if running == total {
+        "UNHEALTHY"
+    } else {
+        "DEGRADED"
+    };
+    format!("{} {}/{}", state, healthy, total)
+}
+
+// Queries docker for the real state of the application, falling back to the stored state
+fn get_status(app: &PersistedApplication, probe: bool) -> String {
+    // A deliberately stopped application is reported as such rather than as exited containers
+    if !probe || app.state == ApplicationState::STOPPED {
+        return app.state.to_string();
+    }
+    match compose_ps_by_id(&app.id) {
+        Ok(containers) => summarise_containers(&containers),
+        Err(e) => {
+            debug!("Could not probe application {}: {}", app.id, e);
+            "UNKNOWN".to_string()
+        }
+    }
+}
+
+fn print_applications(apps: &[PersistedApplication], quiet: bool, wide: bool, probe: bool) {
     if quiet {
         for app in apps {
             println!("{}", app.id);
         }
     } else {
         for app in apps {
-            let time_delta = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_secs() as i64
-                - app.timestamp;
-            let duration = chrono::Duration::seconds(time_delta);
-
-            let time_formatted = HumanTime::from(duration).to_text_en(
-                chrono_humanize::Accuracy::Rough,
-                chrono_humanize::Tense::Present,
-            );
+            let time_formatted = humanize_elapsed(app.timestamp);
+            let status = get_status(app, probe);
             if !wide {
                 // If we aren't printing lots of info
                 info_no_bold!(
@@ -30,7 +78,7 @@ fn print_applications(apps: &[PersistedApplication], quiet: bool, wide: bool) {
                     app_id = app.id,
                     version = app.version,
                     time = time_formatted,
-                    status = app.state,
+                    status = status,
                     app_name = app.app_name
                 );
             } else {
@@ -40,7 +88,7 @@ fn print_applications(apps: &[PersistedApplication], quiet: bool, wide: bool) {
                     app_id = app.id,
                     version = app.version,
                     time = time_formatted,
-                    status = app.state,
+                    status = status,
                     app_name = app.app_name,
                     compose_name = app.compose_path
                 );
@@ -58,9 +106,19 @@ pub struct List {
     /// A more detailed output for each installed application
     #[clap(short, long)]
     wide: bool,
+    /// Shows the state stored when the application was last changed instead of querying docker
+    ///   for the live state of its containers
+    #[clap(long)]
+    no_probe: bool,
 }
 
 impl List {
+    /// Whether the live state of the applications is queried from docker, quiet listings only
+    ///   print the ids
+    pub fn probes(&self) -> bool {
+        !self.quiet && !self.no_probe
+    }
+
     pub fn exec(&self) -> anyhow::Result<()> {
         let all_applications: Vec<PersistedApplication> = get_all_from_storage()?;
         if !self.quiet && !self.wide {
@@ -84,7 +142,49 @@ impl List {
                 compose_name = "COMPOSE"
             );
         }
-        print_applications(&all_applications, self.quiet, self.wide);
+        print_applications(&all_applications, self.quiet, self.wide, !self.no_probe);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(state: &str, health: &str) -> ContainerStatus {
+        ContainerStatus {
+            name: "app-web-1".to_string(),
+            service: "web".to_string(),
+            image: "nginx".to_string(),
+            state: state.to_string(),
+            health: health.to_string(),
+            exit_code: 0,
+            publishers: None,
+        }
+    }
+
+    #[test]
+    fn test_summarise_containers() {
+        assert_eq!(summarise_containers(&[]), "NO CONTAINERS");
+        assert_eq!(
+            summarise_containers(&[container("running", ""), container("running", "healthy")]),
+            "RUNNING 2/2"
+        );
+        assert_eq!(
+            summarise_containers(&[container("running", ""), container("running", "unhealthy")]),
+            "UNHEALTHY 1/2"
+        );
+        assert_eq!(
+            summarise_containers(&[container("running", ""), container("exited", "")]),
+            "DEGRADED 1/2"
+        );
+        assert_eq!(
+            summarise_containers(&[container("exited", ""), container("exited", "")]),
+            "EXITED 0/2"
+        );
+        assert_eq!(
+            summarise_containers(&[container("running", ""), container("restarting", "")]),
+            "RESTARTING 1/2"
+        );
+    }
+}
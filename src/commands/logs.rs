@@ -0,0 +1,137 @@
+use crate::utils::docker_compose::{
+    compose_logs, get_compose_files_by_id, get_compose_service_names,
+};
+use crate::utils::storage::read_from::get_application_by_id;
+use anyhow::anyhow;
+use clap::Args;
+
+#[derive(Debug, Args)]
+pub struct Logs {
+    /// The id of the application to print the logs of
+    #[clap(index = 1)]
+    pub id: String,
+    /// Only print the logs of this service
+    #[clap(index = 2)]
+    pub service: Option<String>,
+    /// Follow the log output
+    #[clap(short, long)]
+    pub follow: bool,
+    /// Number of lines to show from the end of the logs for each container, or "all"
+    #[clap(long)]
+    pub tail: Option<String>,
+    /// Show logs since a timestamp (e.g. 2013-01-02T13:23:37Z) or relative (e.g. 42m for 42 minutes)
+    #[clap(long)]
+    pub since: Option<String>,
+}
+
+impl Logs {
+    // The arguments passed through to docker compose logs
+    fn get_logs_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.follow {
+            args.push("--follow".to_string());
+        }
+        if let Some(tail) = &self.tail {
+            args.push("--tail".to_string());
+            args.push(tail.clone());
+        }
+        if let Some(since) = &self.since {
+            args.push("--since".to_string());
+            args.push(since.clone());
+        }
+        if let Some(service) = &self.service {
+            args.push(service.clone());
+        }
+        args
+    }
+
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        // Fail early if the application is not installed
+        get_application_by_id(&self.id)?;
+        let mut compose_files = get_compose_files_by_id(&self.id)?;
+        if let Some(service) = &self.service {
+            // Compose errors for services it does not know, so only use files defining it
+            compose_files
+                .retain(|compose_file| get_compose_service_names(compose_file).contains(service));
+            if compose_files.is_empty() {
+                return Err(anyhow!(
+                    "Could not find service '{}' in application '{}'.",
+                    service,
+                    self.id
+                ));
+            }
+        }
+        let exit_code = compose_logs(&compose_files, &self.get_logs_args())?;
+        if exit_code != 0 {
+            return Err(anyhow!(
+                "docker compose logs has failed for app {}",
+                self.id
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::load_values::ValueOptions;
+    use super::*;
+    use crate::commands::install::Install;
+    use crate::utils::test_utils::clean_up_test_folder;
+    use relative_path::RelativePath;
+    use serial_test::serial;
+    use std::env::current_dir;
+
+    #[test]
+    fn test_get_logs_args() {
+        let logs_cmd = Logs {
+            id: "app".to_string(),
+            service: Some("web".to_string()),
+            follow: true,
+            tail: Some("10".to_string()),
+            since: Some("5m".to_string()),
+        };
+        assert_eq!(
+            logs_cmd.get_logs_args(),
+            vec!["--follow", "--tail", "10", "--since", "5m", "web"]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_logs_unknown_service() -> anyhow::Result<()> {
+        trace!("Running test_logs_unknown_service.");
+        let id = "test_logs_unknown_service";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        let logs_cmd = Logs {
+            id: id.to_string(),
+            service: Some("does_not_exist".to_string()),
+            follow: false,
+            tail: None,
+            since: None,
+        };
+        let err = logs_cmd.exec().unwrap_err();
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert_eq!(
+            err.to_string(),
+            "Could not find service 'does_not_exist' in application 'test_logs_unknown_service'."
+        );
+        Ok(())
+    }
+}
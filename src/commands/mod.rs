@@ -1,7 +1,15 @@
 pub mod cli;
 mod delete;
+mod diff;
+mod get;
+mod history;
 mod install;
+mod lifecycle;
+mod lint;
 mod list;
+mod logs;
+mod rollback;
+mod status;
 mod template;
 mod test;
 mod upgrade;
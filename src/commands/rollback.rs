@@ -0,0 +1,303 @@
+use crate::app;
+use crate::commands::install::{
+    compose_up_application, get_current_timestamp, get_relative_compose_files,
+    get_staging_directory, restore_previous_application, STAGING_PREVIOUS_DIRECTORY,
+    STAGING_RENDERED_DIRECTORY,
+};
+use crate::utils::copy_file_utils::{copy_files_with_ignorefile, get_composer_directory};
+use crate::utils::docker_compose::compose_down;
+use crate::utils::storage::models::ApplicationState;
+use crate::utils::storage::read_from::get_application_by_id;
+use crate::utils::storage::revisions::{
+    delete_revision, get_revision, get_revision_files_directory, get_revision_values,
+    get_revisions, record_revision, update_revision,
+};
+use crate::utils::storage::write_to_storage::append_to_storage;
+use anyhow::anyhow;
+use clap::Args;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Args)]
+pub struct Rollback {
+    /// The id of the application to roll back
+    #[clap(index = 1)]
+    pub id: String,
+    /// The revision to roll back to, defaults to the previous revision
+    #[clap(index = 2)]
+    pub revision: Option<u32>,
+}
+
+impl Rollback {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        let mut application = get_application_by_id(&self.id)?;
+        let revisions = get_revisions(&self.id)?;
+        let current_revision = match revisions.last() {
+            Some(revision) => revision.revision,
+            None => {
+                return Err(anyhow!(
+                    "No revision history found for application '{}'.",
+                    self.id
+                ));
+            }
+        };
+        let target_revision = match self.revision {
+            Some(revision) => revision,
+            None if revisions.len() > 1 => revisions[revisions.len() - 2].revision,
+            None => {
+                return Err(anyhow!(
+                    "Application '{}' has no previous revision to roll back to.",
+                    self.id
+                ));
+            }
+        };
+        let revision = get_revision(&self.id, target_revision)?;
+        let revision_values = get_revision_values(&self.id, target_revision)?;
+        let revision_files_directory = get_revision_files_directory(&self.id, target_revision)?;
+        if !revision_files_directory.exists() {
+            return Err(anyhow!(
+                "The files for revision {} of application '{}' are missing.",
+                target_revision,
+                self.id
+            ));
+        }
+        info!(
+            "Rolling back application {} from revision {} to revision {}",
+            self.id, current_revision, target_revision
+        );
+
+        let composer_id_directory: PathBuf = get_composer_directory()?.join(&self.id);
+        // Copy the revision into the staging directory, removing anything left over by an
+        // interrupted upgrade or rollback
+        let staging_directory = get_staging_directory(&self.id)?;
+        let rendered_directory = staging_directory.join(STAGING_RENDERED_DIRECTORY);
+        let previous_directory = staging_directory.join(STAGING_PREVIOUS_DIRECTORY);
+        if staging_directory.exists() {
+            fs::remove_dir_all(&staging_directory)?;
+        }
+        let staged = fs::create_dir_all(&rendered_directory)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| {
+                copy_files_with_ignorefile(&revision_files_directory, &rendered_directory, None)
+            });
+        if let Err(e) = staged {
+            fs::remove_dir_all(&staging_directory).ok();
+            return Err(e);
+        }
+        let previous_application = application.clone();
+        application.version = revision.version;
+        application.app_name = revision.app_name;
+        application.compose_path = revision.compose_path;
+        application.values = revision.values;
+        application.timestamp = get_current_timestamp();
+        application.state = ApplicationState::STARTING;
+        // Record the revision from the staged files, before any runtime data can be written next
+        // to them
+        let description = format!("Rollback to {}", target_revision);
+        let recorded = match record_revision(
+            &application,
+            &rendered_directory,
+            revision_values.as_ref(),
+            &description,
+        ) {
+            Ok(recorded) => recorded.revision,
+            Err(e) => {
+                fs::remove_dir_all(&staging_directory).ok();
+                return Err(e);
+            }
+        };
+        if composer_id_directory.exists() {
+            // Bring down compose files that do not exist in the target revision, compose up
+            // with --remove-orphans only cleans up services within the same file
+            let target_compose_files = get_relative_compose_files(&revision_files_directory);
+            for compose_file in get_relative_compose_files(&composer_id_directory) {
+                if !target_compose_files.contains(&compose_file) {
+                    let compose_path = composer_id_directory.join(&compose_file);
+                    compose_down(compose_path.to_str().unwrap(), &self.id)?;
+                }
+            }
+            // Swap the revision in, keeping the current files until the application is up
+            fs::rename(&composer_id_directory, &previous_directory)?;
+        }
+        fs::rename(&rendered_directory, &composer_id_directory)?;
+
+        if let Err(e) = compose_up_application(&self.id, &composer_id_directory) {
+            // The rollback never came up, so the revision is not kept in its history
+            delete_revision(&self.id, recorded)?;
+            if previous_directory.exists() {
+                restore_previous_application(
+                    &previous_application,
+                    &composer_id_directory,
+                    &previous_directory,
+                )?;
+                fs::remove_dir_all(&staging_directory)?;
+                return Err(e.context(format!(
+                    "Rollback of application {} has failed and the previous files have been restored",
+                    self.id
+                )));
+            }
+            fs::remove_dir_all(&staging_directory)?;
+            return Err(e);
+        }
+        fs::remove_dir_all(&staging_directory)?;
+
+        if !*app::no_run() {
+            application.state = ApplicationState::RUNNING;
+        }
+        append_to_storage(&application)?;
+        update_revision(&self.id, recorded, application.state.clone(), &description)?;
+        success!(
+            "Rolled back application {} to revision {}",
+            self.id,
+            target_revision
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::load_values::ValueOptions;
+    use super::*;
+    use crate::commands::install::Install;
+    use crate::commands::upgrade::Upgrade;
+    use crate::utils::test_utils::clean_up_test_folder;
+    use relative_path::RelativePath;
+    use serial_test::serial;
+    use std::env::current_dir;
+
+    #[test]
+    #[serial]
+    fn test_rollback_without_history() -> anyhow::Result<()> {
+        trace!("Running test_rollback_without_history.");
+        let rollback_cmd = Rollback {
+            id: "test_rollback_without_history".to_string(),
+            revision: None,
+        };
+        // The application does not exist so it can't be rolled back
+        assert!(rollback_cmd.exec().is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_rollback_to_previous_revision() -> anyhow::Result<()> {
+        trace!("Running test_rollback_to_previous_revision.");
+        let id = "test_rollback_to_previous_revision";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let override_str = RelativePath::new("resources/test/test_values/override.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        clean_up_test_folder(id)?;
+
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        Upgrade {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![override_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
+        }
+        .exec()?;
+        Rollback {
+            id: id.to_string(),
+            revision: None,
+        }
+        .exec()?;
+
+        let app = get_application_by_id(id)?;
+        let revisions = get_revisions(id)?;
+        let rendered =
+            fs::read_to_string(get_composer_directory()?.join(id).join("docker-compose.jinja2"))?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        assert_eq!(app.values.value_files, vec![values_str]);
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(revisions[2].description, "Rollback to 1");
+        assert!(rendered.contains("\"string\""));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_failed_rollback_keeps_current_files() -> anyhow::Result<()> {
+        trace!("Running test_failed_rollback_keeps_current_files.");
+        let id = "test_failed_rollback_keeps_current_files";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        let override_str = RelativePath::new("resources/test/test_values/override.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        clean_up_test_folder(id)?;
+
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        Upgrade {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![override_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
+        }
+        .exec()?;
+        // The compose file of the first revision is invalid so compose up fails
+        fs::write(
+            get_revision_files_directory(id, 1)?.join("docker-compose.jinja2"),
+            "services: [",
+        )?;
+        let composer_id_directory = get_composer_directory()?.join(id);
+        let upgraded = fs::read_to_string(composer_id_directory.join("docker-compose.jinja2"))?;
+        let result = Rollback {
+            id: id.to_string(),
+            revision: Some(1),
+        }
+        .exec();
+        let rendered = fs::read_to_string(composer_id_directory.join("docker-compose.jinja2"));
+        let staging_exists = get_staging_directory(id)?.exists();
+        let app = get_application_by_id(id)?;
+        let revisions = get_revisions(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        assert!(result.is_err());
+        assert_eq!(rendered?, upgraded);
+        assert!(!staging_exists);
+        assert_eq!(app.values.value_files, vec![override_str]);
+        assert_eq!(revisions.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::commands::cli::OutputFormat;
+use crate::commands::list::summarise_containers;
+use crate::utils::copy_file_utils::get_composer_directory;
+use crate::utils::docker_compose::{
+    compose_ps, container_restart_count, get_compose_files_by_id, get_compose_service_names,
+    ContainerStatus, PublishedPort,
+};
+use crate::utils::storage::read_from::get_application_by_id;
+use clap::Args;
+use serde::Serialize;
+use std::path::Path;
+
+/// The live state of a single service of an application
+#[derive(Debug, Serialize, PartialEq)]
+struct ServiceStatus {
+    compose_file: String,
+    service: String,
+    container: String,
+    image: String,
+    state: String,
+    health: String,
+    ports: Vec<String>,
+    restarts: Option<u32>,
+}
+
+fn format_port(port: &PublishedPort) -> String {
+    if port.published_port == 0 {
+        format!("{}/{}", port.target_port, port.protocol)
+    } else {
+        format!(
+            "{}:{}->{}/{}",
+            port.url, port.published_port, port.target_port, port.protocol
+        )
+    }
+}
+
+// Combines the services defined in a compose file with the containers docker reports for it,
+// services without a container are reported as not created
+fn get_service_statuses(
+    compose_file: &str,
+    relative_compose_file: &str,
+    containers: &[ContainerStatus],
+) -> Vec<ServiceStatus> {
+    let mut statuses: Vec<ServiceStatus> = containers
+        .iter()
+        .map(|container| ServiceStatus {
+            compose_file: relative_compose_file.to_string(),
+            service: container.service.clone(),
+            container: container.name.clone(),
+            image: container.image.clone(),
+            state: container.state.clone(),
+            health: container.health.clone(),
+            ports: container
+                .publishers
+                .iter()
+                .flatten()
+                .map(format_port)
+                .collect(),
+            restarts: container_restart_count(&container.name).ok(),
+        })
+        .collect();
+    for service in get_compose_service_names(compose_file) {
+        if !containers.iter().any(|container| container.service == service) {
+            statuses.push(ServiceStatus {
+                compose_file: relative_compose_file.to_string(),
+                service,
+                container: String::new(),
+                image: String::new(),
+                state: "not created".to_string(),
+                health: String::new(),
+                ports: Vec::new(),
+                restarts: None,
+            });
+        }
+    }
+    statuses
+}
+
+fn print_service_statuses(statuses: &[ServiceStatus]) {
+    for status in statuses {
+        info_no_bold!(
+            "{service:<20} {container:<30} {image:<30} {state:<12} {health:<10} {restarts:<9} {ports}",
+            service = status.service,
+            container = status.container,
+            image = status.image,
+            state = status.state,
+            health = status.health,
+            restarts = status
+                .restarts
+                .map(|restarts| restarts.to_string())
+                .unwrap_or_default(),
+            ports = status.ports.join(", ")
+        );
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Status {
+    /// The id of the application to show the status of
+    #[clap(index = 1)]
+    pub id: String,
+    /// The output format, json prints every service as a machine readable array
+    #[clap(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+impl Status {
+    pub fn exec(&self) -> anyhow::Result<()> {
+        trace!("Command: {:?}", self);
+        let application = get_application_by_id(&self.id)?;
+        let composer_id_directory = get_composer_directory()?.join(&self.id);
+
+        let mut containers = Vec::new();
+        let mut statuses = Vec::new();
+        for compose_file in get_compose_files_by_id(&self.id)? {
+            let relative_compose_file = Path::new(&compose_file)
+                .strip_prefix(&composer_id_directory)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| compose_file.clone());
+            let file_containers = compose_ps(&compose_file)?;
+            statuses.extend(get_service_statuses(
+                &compose_file,
+                &relative_compose_file,
+                &file_containers,
+            ));
+            containers.extend(file_containers);
+        }
+
+        if self.output == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(&statuses)?);
+            return Ok(());
+        }
+        info!(
+            "{} ({} {}): {}",
+            application.id,
+            application.app_name,
+            application.version,
+            summarise_containers(&containers)
+        );
+        info!(
+            "{service:<20} {container:<30} {image:<30} {state:<12} {health:<10} {restarts:<9} {ports}",
+            service = "SERVICE",
+            container = "CONTAINER",
+            image = "IMAGE",
+            state = "STATE",
+            health = "HEALTH",
+            restarts = "RESTARTS",
+            ports = "PORTS"
+        );
+        print_service_statuses(&statuses);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_format_port() {
+        let published = PublishedPort {
+            url: "0.0.0.0".to_string(),
+            target_port: 80,
+            published_port: 8080,
+            protocol: "tcp".to_string(),
+        };
+        assert_eq!(format_port(&published), "0.0.0.0:8080->80/tcp");
+        let exposed = PublishedPort {
+            url: String::new(),
+            target_port: 5432,
+            published_port: 0,
+            protocol: "tcp".to_string(),
+        };
+        assert_eq!(format_port(&exposed), "5432/tcp");
+    }
+
+    #[test]
+    fn test_service_without_container_is_not_created() -> anyhow::Result<()> {
+        let compose = NamedTempFile::new()?;
+        fs::write(compose.path(), "services:\n  web:\n    image: nginx\n")?;
+        let compose_path = compose.path().to_str().unwrap();
+        let statuses = get_service_statuses(compose_path, "docker-compose.jinja2", &[]);
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].service, "web");
+        assert_eq!(statuses[0].state, "not created");
+        Ok(())
+    }
+}
@@ -1,19 +1,22 @@
 use anyhow::anyhow;
 
-use crate::utils::load_values::{get_value_files_as_refs, load_yaml_files};
+use crate::app;
+use crate::utils::load_values::ValueOptions;
+use crate::utils::storage::app_yaml::load_app_yaml;
 use crate::utils::template::render_template;
+use crate::utils::values_schema::validate_values;
 
 use clap::Args;
 
 use std::fs::write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Args)]
 pub struct Template {
     #[arg(short, long)]
     pub template: PathBuf,
-    #[arg(short, long)]
-    pub value_files: Vec<String>,
+    #[clap(flatten)]
+    pub values: ValueOptions,
     #[arg(short, long, default_value_t = String::new())]
     pub output_file: String,
 }
@@ -28,14 +31,16 @@ impl Template {
             ));
         }
 
-        if self.value_files.is_empty() {
+        // The values.yaml next to the template is used as the lowest precedence layer
+        let template_directory = self.template.parent().unwrap_or_else(|| Path::new("."));
+        let values = self.values.with_default_values(template_directory);
+        if values.is_empty() {
             return Err(anyhow!(
                 "You cannot create a template with no values file. Use -v <values path> to specify values file."
             ));
         }
 
-        let values: Vec<&str> = get_value_files_as_refs(&self.value_files);
-        let consolidated_values: serde_yaml::Value = load_yaml_files(&values)?;
+        let consolidated_values: serde_yaml::Value = values.load()?;
 
         trace!(
             "Consolidated values: \n```\n{}\n```\n",
@@ -47,7 +52,13 @@ impl Template {
             .to_str()
             .expect("Could not get path to template file.");
 
-        let rendered_template = render_template(template_path_str, consolidated_values)?;
+        // Validate against the values schema and use strict mode if enabled globally or by the
+        // app.yaml next to the template
+        validate_values(template_directory, &consolidated_values)?;
+        let app_yaml_path = self.template.with_file_name("app.yaml");
+        let strict = *app::strict()
+            || (app_yaml_path.exists() && load_app_yaml(&app_yaml_path)?.strict);
+        let rendered_template = render_template(template_path_str, consolidated_values, strict)?;
 
         if self.output_file.is_empty() {
             // Print output to console
@@ -63,6 +74,7 @@ impl Template {
 
 #[cfg(test)]
 mod tests {
+    use crate::utils::load_values::ValueOptions;
     use relative_path::RelativePath;
     use tempfile::{tempdir, TempDir};
 
@@ -85,7 +97,7 @@ mod tests {
 
         let test_template_cmd: Template = Template {
             template: template_path.to_owned(),
-            value_files: vec![values_path.to_str().unwrap().to_owned()],
+            values: ValueOptions::from_value_files(vec![values_path.to_str().unwrap().to_owned()]),
             output_file: String::new(),
         };
 
@@ -110,7 +122,7 @@ mod tests {
 
         let test_template_cmd: Template = Template {
             template: template_path.to_owned(),
-            value_files: vec![values_path.to_str().unwrap().to_owned()],
+            values: ValueOptions::from_value_files(vec![values_path.to_str().unwrap().to_owned()]),
             output_file: output_path.to_str().unwrap().to_owned(),
         };
 
@@ -128,7 +140,7 @@ mod tests {
 
         let test_template_cmd: Template = Template {
             template: PathBuf::new(),
-            value_files: vec![],
+            values: ValueOptions::default(),
             output_file: String::new(),
         };
 
@@ -153,7 +165,7 @@ mod tests {
 
         let test_template_cmd: Template = Template {
             template: template_path.to_owned(),
-            value_files: vec![],
+            values: ValueOptions::default(),
             output_file: String::new(),
         };
 
@@ -1,9 +1,12 @@
-use crate::commands::install::add_application;
+use crate::commands::install::{add_application, dry_run_application};
 use crate::utils::copy_file_utils::get_composer_directory;
+use crate::utils::load_values::{ReusedValues, ValueOptions, DEFAULT_VALUES_FILE};
+use crate::utils::storage::models::PersistedApplication;
 use crate::utils::storage::read_from::get_application_by_id;
+use crate::utils::storage::revisions::{get_revision_values, get_revisions};
+use crate::utils::wait::WaitOptions;
 use anyhow::anyhow;
 use clap::Args;
-use std::fs::remove_dir_all;
 use std::path::PathBuf;
 
 #[derive(Debug, Args)]
@@ -12,8 +15,46 @@ pub struct Upgrade {
     pub directory: PathBuf,
     #[clap(short, long)]
     pub id: Option<String>,
-    #[clap(short, long)]
-    pub value_files: Vec<String>,
+    #[clap(flatten)]
+    pub values: ValueOptions,
+    /// Render and validate the upgrade without applying it, ~/.composer and docker are left
+    ///   untouched
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Used with --dry-run, writes the rendered application to this directory instead of
+    ///   printing the rendered templates
+    #[clap(long, requires = "dry_run")]
+    pub output_dir: Option<PathBuf>,
+    #[clap(flatten)]
+    pub wait: WaitOptions,
+    /// Reuse the values of the previous revision and layer the given values over them, this is
+    ///   the default if no values are given
+    #[clap(long)]
+    pub reuse_values: bool,
+    /// Ignore the values of the previous revision, only the template's default values and the
+    ///   given values are used
+    #[clap(long, conflicts_with = "reuse_values")]
+    pub reset_values: bool,
+}
+
+/// Returns the values an upgrade reuses with the given values layered over them. The user values
+/// snapshot of the latest revision is reused so edited or deleted value files don't change it, see
+/// `ReusedValues`. Applications recorded before values were snapshotted reuse their stored values
+/// instead.
+pub fn get_previous_values(
+    application: &PersistedApplication,
+    values: &ValueOptions,
+) -> anyhow::Result<(ValueOptions, Option<ReusedValues>)> {
+    if let Some(revision) = get_revisions(&application.id)?.last() {
+        if let Some(snapshot) = get_revision_values(&application.id, revision.revision)? {
+            let reused = ReusedValues {
+                snapshot: snapshot.user_values,
+                options: application.values.clone(),
+            };
+            return Ok((values.clone(), Some(reused)));
+        }
+    }
+    Ok((application.values.layered_with(values), None))
 }
 
 impl Upgrade {
@@ -41,32 +82,47 @@ impl Upgrade {
             )));
         }
 
-        // Determine the value files to use
-        let value_files = if self.value_files.is_empty() {
-            // Retrieve the persisted application
-            let application = get_application_by_id(install_id)?;
-            // Use the previously stored value files
-            if application.value_files.is_empty() {
+        // Determine the values to use, mirroring helm the previous values are only reused if no
+        // values are given or if asked to
+        let application = get_application_by_id(install_id)?;
+        let (values, reused) = if self.reset_values
+            || (!self.reuse_values && !self.values.is_empty())
+        {
+            (self.values.clone(), None)
+        } else {
+            let (values, reused) = get_previous_values(&application, &self.values)?;
+            if reused.is_none()
+                && values.is_empty()
+                && !self.directory.join(DEFAULT_VALUES_FILE).exists()
+            {
                 return Err(anyhow!(
                     "Cannot upgrade application '{}' because no value files were provided and none were found from the previous installation. Use -v <values path> to specify value files.",
                     install_id
                 ));
             }
-            application.value_files.clone()
-        } else {
-            self.value_files.clone()
+            (values, reused)
         };
 
-        // First remove the existing directory
-        remove_dir_all(&composer_id_directory)?;
+        if self.dry_run {
+            return dry_run_application(
+                true,
+                &values,
+                reused.as_ref(),
+                &self.directory,
+                self.output_dir.as_deref(),
+            );
+        }
+
         info!("Upgrading application with ID: {}", install_id);
 
         add_application(
             install_id,
             &composer_id_directory,
             true,
-            &value_files,
+            &values,
+            reused.as_ref(),
             &self.directory,
+            &self.wait,
         )?;
 
         Ok(())
@@ -76,6 +132,7 @@ impl Upgrade {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::install::Install;
     use crate::utils::storage::models::{ApplicationState, PersistedApplication};
     use crate::utils::storage::read_from::get_application_by_id;
     use crate::utils::storage::write_to_storage::append_to_storage;
@@ -84,7 +141,7 @@ mod tests {
     use serial_test::serial;
     use std::env::current_dir;
     use std::fs;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     #[test]
     #[serial]
@@ -94,7 +151,12 @@ mod tests {
         let upgrade_cmd = Upgrade {
             directory: PathBuf::from("some/directory"),
             id: None,
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
         };
         let err = upgrade_cmd.exec().unwrap_err();
         let actual_err = err.to_string();
@@ -115,7 +177,12 @@ mod tests {
         let upgrade_cmd = Upgrade {
             directory: upgrade_dir,
             id: Some(id.to_string()),
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
         };
         let err = upgrade_cmd.exec().unwrap_err();
         let actual_err = err.to_string();
@@ -153,7 +220,7 @@ mod tests {
             state: ApplicationState::RUNNING,
             app_name: "Test App".to_string(),
             compose_path: install_dir.to_string_lossy().to_string(),
-            value_files: vec![], // Empty value_files
+            values: ValueOptions::default(), // No stored values
         };
         append_to_storage(&app)?;
 
@@ -161,7 +228,12 @@ mod tests {
         let upgrade_cmd = Upgrade {
             directory: install_dir.clone(),
             id: Some(id.to_string()),
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
         };
 
         let err = upgrade_cmd.exec().unwrap_err();
@@ -176,6 +248,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial]
+    fn test_upgrade_with_only_default_values() -> anyhow::Result<()> {
+        trace!("Running test_upgrade_with_only_default_values.");
+        let id = "test_upgrade_with_only_default_values";
+        let current_dir = current_dir()?;
+        let install_dir =
+            RelativePath::new("resources/test/defaults/").to_logical_path(&current_dir);
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        // Nothing is stored or provided but the template has its own values.yaml
+        let result = Upgrade {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
+        }
+        .exec();
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+        assert!(result.is_ok());
+        Ok(())
+    }
+
     #[test]
     #[serial]
     fn test_upgrade_with_provided_value_files() -> anyhow::Result<()> {
@@ -205,7 +312,7 @@ mod tests {
             state: ApplicationState::RUNNING,
             app_name: "Test App".to_string(),
             compose_path: install_dir.to_string_lossy().to_string(),
-            value_files: vec![values_str.clone()],
+            values: ValueOptions::from_value_files(vec![values_str.clone()]),
         };
         append_to_storage(&app)?;
 
@@ -217,7 +324,12 @@ mod tests {
         let upgrade_cmd = Upgrade {
             directory: install_dir.clone(),
             id: Some(id.to_string()),
-            value_files: vec![new_values_str.clone()],
+            values: ValueOptions::from_value_files(vec![new_values_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
         };
 
         upgrade_cmd.exec()?;
@@ -227,7 +339,7 @@ mod tests {
         // Clean up before assertions in case they fail
         clean_up_test_folder(id)?;
 
-        assert_eq!(app.value_files, vec![new_values_str]);
+        assert_eq!(app.values.value_files, vec![new_values_str]);
         assert_eq!(app.state, ApplicationState::RUNNING);
         Ok(())
     }
@@ -261,7 +373,7 @@ mod tests {
             state: ApplicationState::RUNNING,
             app_name: "Test App".to_string(),
             compose_path: install_dir.to_string_lossy().to_string(),
-            value_files: vec![values_str.clone()],
+            values: ValueOptions::from_value_files(vec![values_str.clone()]),
         };
         append_to_storage(&app)?;
 
@@ -269,7 +381,12 @@ mod tests {
         let upgrade_cmd = Upgrade {
             directory: install_dir.clone(),
             id: Some(id.to_string()),
-            value_files: vec![],
+            values: ValueOptions::default(),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
         };
 
         upgrade_cmd.exec()?;
@@ -279,8 +396,280 @@ mod tests {
         // Clean up before assertions in case they fail
         clean_up_test_folder(id)?;
 
-        assert_eq!(app.value_files, vec![values_str]);
+        assert_eq!(app.values.value_files, vec![values_str]);
         assert_eq!(app.state, ApplicationState::RUNNING);
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Upgrades the application with the given values and returns its rendered compose file
+    fn upgrade_and_render(
+        id: &str,
+        install_dir: &Path,
+        values: ValueOptions,
+        reuse_values: bool,
+        reset_values: bool,
+    ) -> anyhow::Result<String> {
+        Upgrade {
+            directory: install_dir.to_path_buf(),
+            id: Some(id.to_string()),
+            values,
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values,
+            reset_values,
+        }
+        .exec()?;
+        Ok(fs::read_to_string(
+            get_composer_directory()?
+                .join(id)
+                .join("docker-compose.jinja2"),
+        )?)
+    }
+
+    #[test]
+    #[serial]
+    fn test_upgrade_reuses_values_snapshot() -> anyhow::Result<()> {
+        trace!("Running test_upgrade_reuses_values_snapshot.");
+        let id = "test_upgrade_reuses_values_snapshot";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_directory = tempfile::tempdir()?;
+        let values_file = values_directory.path().join("values.yaml");
+        fs::write(&values_file, "world: installed\n")?;
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions {
+                value_files: vec![values_file.to_string_lossy().to_string()],
+                set: vec!["hello=false".to_string()],
+                ..Default::default()
+            },
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        // Editing the values file after install doesn't change the reused values
+        fs::write(&values_file, "world: edited\n")?;
+        let reused = upgrade_and_render(id, &install_dir, ValueOptions::default(), false, false);
+        let reused_values = get_revision_values(id, 2);
+        // New values are layered over the previous values with --reuse-values
+        let layered = upgrade_and_render(
+            id,
+            &install_dir,
+            ValueOptions {
+                set: vec!["extra=1".to_string()],
+                ..Default::default()
+            },
+            true,
+            false,
+        );
+        let layered_values = get_revision_values(id, 3);
+        // Only the given values are used with --reset-values
+        let reset = upgrade_and_render(
+            id,
+            &install_dir,
+            ValueOptions {
+                set: vec!["world=reset".to_string()],
+                ..Default::default()
+            },
+            false,
+            true,
+        );
+        let reset_values = get_revision_values(id, 4);
+        let app = get_application_by_id(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        assert!(reused?.contains("\"installed\""));
+        let reused_values = reused_values?.unwrap();
+        assert_eq!(
+            reused_values.user_values,
+            serde_yaml::from_str::<serde_yaml::Value>("world: installed\nhello: false\n")?
+        );
+        assert!(layered?.contains("\"installed\""));
+        assert_eq!(
+            layered_values?.unwrap().user_values,
+            serde_yaml::from_str::<serde_yaml::Value>(
+                "world: installed\nhello: false\nextra: 1\n"
+            )?
+        );
+        assert!(reset?.contains("\"reset\""));
+        assert_eq!(
+            reset_values?.unwrap().user_values,
+            serde_yaml::from_str::<serde_yaml::Value>("world: reset\n")?
+        );
+        assert_eq!(app.values.set, vec!["world=reset".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_reused_values_keep_merge_annotations() -> anyhow::Result<()> {
+        trace!("Running test_reused_values_keep_merge_annotations.");
+        let id = "test_reused_values_keep_merge_annotations";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let merge_lists_dir =
+            RelativePath::new("resources/test/merge_lists/").to_logical_path(&current_dir);
+        let annotated = merge_lists_dir.join("annotated.yaml");
+        let annotated_override = merge_lists_dir.join("annotated_override.yaml");
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions {
+                value_files: vec![annotated.to_string_lossy().to_string()],
+                set: vec!["world=annotated".to_string()],
+                ..Default::default()
+            },
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        // The override is merged with the strategies annotated by the installed values file
+        let merged = upgrade_and_render(
+            id,
+            &install_dir,
+            ValueOptions::from_value_files(vec![annotated_override.to_string_lossy().to_string()]),
+            true,
+            false,
+        );
+        let merged_values = get_revision_values(id, 2);
+        let layered = upgrade_and_render(
+            id,
+            &install_dir,
+            ValueOptions {
+                set: vec!["extra=2".to_string()],
+                ..Default::default()
+            },
+            true,
+            false,
+        );
+        let app = get_application_by_id(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        merged?;
+        layered?;
+        let user_values = merged_values?.unwrap().user_values;
+        assert_eq!(
+            user_values["ports"],
+            serde_yaml::from_str::<serde_yaml::Value>("[8080]")?
+        );
+        assert_eq!(
+            user_values["services"],
+            serde_yaml::from_str::<serde_yaml::Value>(
+                "[{name: web, image: caddy}, {name: db, image: postgres}, {name: cache, image: redis}]"
+            )?
+        );
+        assert_eq!(
+            user_values["composer"]["merge"],
+            serde_yaml::from_str::<serde_yaml::Value>(
+                "{ports: replace, services: 'merge-by-key:name'}"
+            )?
+        );
+        // The values given by the user are stored, not the snapshot they were layered over
+        assert_eq!(
+            app.values.value_files,
+            vec![
+                annotated.to_string_lossy().to_string(),
+                annotated_override.to_string_lossy().to_string()
+            ]
+        );
+        assert_eq!(
+            app.values.set,
+            vec!["world=annotated".to_string(), "extra=2".to_string()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_reused_set_values_take_precedence_over_environment() -> anyhow::Result<()> {
+        trace!("Running test_reused_set_values_take_precedence_over_environment.");
+        let id = "test_reused_set_values_take_precedence";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir.clone(),
+            id: Some(id.to_string()),
+            values: ValueOptions {
+                value_files: vec![values_str.clone()],
+                set: vec!["world=installed".to_string()],
+                ..Default::default()
+            },
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        std::env::set_var("COMPOSER_VALUE_world", "environment");
+        let reused = upgrade_and_render(id, &install_dir, ValueOptions::default(), true, false);
+        std::env::remove_var("COMPOSER_VALUE_world");
+        let app = get_application_by_id(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        assert!(reused?.contains("\"installed\""));
+        // The stored values are the ones the user gave, not the snapshot of the previous revision
+        assert_eq!(app.values.value_files, vec![values_str]);
+        assert_eq!(app.values.set, vec!["world=installed".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_failed_upgrade_keeps_previous_files() -> anyhow::Result<()> {
+        trace!("Running test_failed_upgrade_keeps_previous_files.");
+        let id = "test_failed_upgrade_keeps_previous_files";
+        let current_dir = current_dir()?;
+        let install_dir = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let schema_dir = RelativePath::new("resources/test/schema/").to_logical_path(&current_dir);
+        let values_str = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+        Install {
+            directory: install_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str.clone()]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+        }
+        .exec()?;
+        let composer_id_directory = get_composer_directory()?.join(id);
+        let installed = fs::read_to_string(composer_id_directory.join("docker-compose.jinja2"))?;
+        // The values don't match the schema of the new template so rendering fails
+        let result = Upgrade {
+            directory: schema_dir,
+            id: Some(id.to_string()),
+            values: ValueOptions::from_value_files(vec![values_str]),
+            dry_run: false,
+            output_dir: None,
+            wait: Default::default(),
+            reuse_values: false,
+            reset_values: false,
+        }
+        .exec();
+        let upgraded = fs::read_to_string(composer_id_directory.join("docker-compose.jinja2"));
+        let staging_exists = get_composer_directory()?.join(".staging").join(id).exists();
+        let app = get_application_by_id(id)?;
+        let revisions = get_revisions(id)?;
+        // Clean up before assertions in case they fail
+        clean_up_test_folder(id)?;
+
+        assert!(result.is_err());
+        assert_eq!(upgraded?, installed);
+        assert!(!staging_exists);
+        assert!(app.compose_path.ends_with("simple"));
+        assert_eq!(revisions.len(), 1);
+        Ok(())
+    }
+}
@@ -10,7 +10,7 @@ use crate::commands::cli::Cli;
 use log::LevelFilter;
 use std::str::FromStr;
 
-use crate::utils::docker_compose::is_compose_installed;
+use crate::utils::docker_compose::ComposeError;
 use clap::Parser;
 
 #[cfg(test)]
@@ -34,16 +34,26 @@ fn main() -> anyhow::Result<()> {
     app::set_global_verbosity(log_level);
     app::set_global_always_pull(cli.always_pull);
     app::set_global_no_run(cli.no_run);
-    if !is_compose_installed() {
-        error!("Docker-compose is not installed. Please install it before using composer.");
-        std::process::exit(1);
+    app::set_global_strict(cli.strict);
+    app::set_global_runtime_selection(cli.runtime);
+    // Commands running compose fail early without a runtime, the others resolve it on first use
+    if cli.requires_compose() {
+        if let Err(e) = app::runtime() {
+            error!("{}", e);
+            std::process::exit(1);
+        }
     }
     let result = cli.run();
     match result {
         Ok(_) => {}
         Err(e) => {
-            error!("{}", e);
-            std::process::exit(1);
+            error!("{:#}", e);
+            // Failed docker compose commands pass on their exit code
+            let exit_code = e
+                .downcast_ref::<ComposeError>()
+                .map(ComposeError::exit_code)
+                .unwrap_or(1);
+            std::process::exit(exit_code);
         }
     }
     Ok(())
@@ -1,35 +1,477 @@
+use crate::app;
+use crate::utils::copy_file_utils::get_composer_directory;
 use crate::utils::storage::models::ApplicationState::ERROR;
 use crate::utils::storage::update_storage::update_application_state;
+use crate::utils::walk::get_files_with_name;
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::Mutex;
 
-pub fn unbuffered_command(command_line_args: &[&str]) -> i32 {
+/// The prefix of the labels added to every container started by composer
+pub const COMPOSER_LABEL_PREFIX: &str = "com.bytesquid.composer";
+/// The label holding the compose service of a container
+const SERVICE_LABEL: &str = "com.bytesquid.composer.service";
+/// The label compose itself adds with the project of a container, used to list the containers of
+/// runtimes that can't print `compose ps` as JSON, including ones started before composer labelled
+/// its containers
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+/// The label compose itself adds with the directory it ran in
+const COMPOSE_WORKING_DIR_LABEL: &str = "com.docker.compose.project.working_dir";
+/// The label compose itself adds with the service of a container
+const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+
+/// The legacy project every compose file was found running under, if any, so the containers are
+/// only queried once per file however many compose commands a composer command runs
+static LEGACY_PROJECTS: Lazy<Mutex<HashMap<String, Option<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// An error reading a compose file or running docker compose.
+///
+/// Functions returning `anyhow::Result` wrap these so callers, e.g. `main` choosing the exit code,
+/// can still find them with `downcast_ref`.
+#[derive(Debug)]
+pub enum ComposeError {
+    /// The compose file does not exist
+    FileNotFound(String),
+    /// The compose file is empty
+    EmptyFile(String),
+    /// The compose file is not valid YAML
+    InvalidYaml {
+        path: String,
+        source: serde_yaml::Error,
+    },
+    /// The command could not be found, e.g. docker is not installed
+    CommandNotFound(String),
+    /// The command ran but exited with a non-zero exit code
+    CommandFailed {
+        command: String,
+        exit_code: i32,
+        stderr: String,
+    },
+    /// Any other IO error reading a compose file or running a command
+    Io {
+        description: String,
+        source: std::io::Error,
+    },
+}
+
+impl ComposeError {
+    /// The exit code composer should exit with, the exit code of a failed command is passed on
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ComposeError::CommandFailed { exit_code, .. } if *exit_code > 0 => *exit_code,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComposeError::FileNotFound(path) => {
+                write!(
+                    f,
+                    "The provided compose file path '{}' does not exist.",
+                    path
+                )
+            }
+            ComposeError::EmptyFile(path) => {
+                write!(f, "The provided compose file '{}' is empty.", path)
+            }
+            ComposeError::InvalidYaml { path, source } => write!(
+                f,
+                "The provided compose file '{}' is not a valid YAML file: {}",
+                path, source
+            ),
+            ComposeError::CommandNotFound(command) => {
+                write!(f, "Could not find '{}', is it installed?", command)
+            }
+            ComposeError::CommandFailed {
+                command,
+                exit_code,
+                stderr,
+            } => {
+                write!(f, "'{}' exited with code {}", command, exit_code)?;
+                if !stderr.trim().is_empty() {
+                    write!(f, ": {}", stderr.trim())?;
+                }
+                Ok(())
+            }
+            ComposeError::Io {
+                description,
+                source,
+            } => write!(f, "{}: {}", description, source),
+        }
+    }
+}
+
+impl std::error::Error for ComposeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ComposeError::InvalidYaml { source, .. } => Some(source),
+            ComposeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Spawning a command fails with NotFound if it is not on the PATH
+fn spawn_error(command_line_args: &[&str], error: std::io::Error) -> ComposeError {
+    if error.kind() == std::io::ErrorKind::NotFound {
+        ComposeError::CommandNotFound(command_line_args[0].to_string())
+    } else {
+        ComposeError::Io {
+            description: format!("Could not run '{}'", command_line_args.join(" ")),
+            source: error,
+        }
+    }
+}
+
+/// The compose project a rendered compose file of an installed application is run as
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComposeProject {
+    /// The id of the application the compose file belongs to
+    pub application_id: String,
+    /// The path of the compose file relative to the application's directory
+    pub compose_file: String,
+    /// The name passed to docker compose with `-p`
+    pub name: String,
+}
+
+/// A container belonging to a compose project as reported by `docker compose ps --format json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerStatus {
+    pub name: String,
+    pub service: String,
+    #[serde(default)]
+    pub image: String,
+    pub state: String,
+    #[serde(default)]
+    pub health: String,
+    #[serde(default)]
+    pub exit_code: i32,
+    #[serde(default)]
+    pub publishers: Option<Vec<PublishedPort>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishedPort {
+    #[serde(rename = "URL", default)]
+    pub url: String,
+    #[serde(rename = "TargetPort")]
+    pub target_port: u16,
+    #[serde(rename = "PublishedPort", default)]
+    pub published_port: u16,
+    #[serde(rename = "Protocol", default)]
+    pub protocol: String,
+}
+
+/// Returns every rendered docker-compose.jinja2 file belonging to an installed application
+pub fn get_compose_files_by_id(id: &str) -> anyhow::Result<Vec<String>> {
+    let composer_id_directory: PathBuf = get_composer_directory()?.join(id);
+    Ok(get_files_with_name(
+        composer_id_directory.to_str().unwrap(),
+        "docker-compose.jinja2",
+    ))
+}
+
+/// Returns the compose project of a rendered compose file under `~/.composer/<id>`.
+///
+/// The project name is derived from the application id and the directory of the compose file
+/// within the application, e.g. `composer-my_app-1a2b3c4d` or `composer-my_app-nested-db-5e6f7a8b`,
+/// instead of docker compose's default of the directory name, so nested compose files of two
+/// applications in identically named directories never share a project. The readable part only
+/// keeps the characters compose allows, so it ends with a short hash of the unmodified id and
+/// directory which keeps e.g. `my.app` and `my-app` apart.
+pub fn get_compose_project(path: &str) -> anyhow::Result<ComposeProject> {
+    let composer_directory = get_composer_directory()?;
+    let relative_path = Path::new(path)
+        .strip_prefix(&composer_directory)
+        .map_err(|_| {
+            anyhow!(
+                "Compose file {} is not part of an application in {}",
+                path,
+                composer_directory.display()
+            )
+        })?;
+    let mut components = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string());
+    let application_id = components
+        .next()
+        .ok_or_else(|| anyhow!("Compose file {} does not belong to an application", path))?;
+    let compose_file: Vec<String> = components.collect();
+    let mut name_parts = vec![application_id.clone()];
+    // The last component is the file name itself which is always docker-compose.jinja2
+    if let Some((_, directories)) = compose_file.split_last() {
+        name_parts.extend(directories.iter().cloned());
+    }
+    Ok(ComposeProject {
+        name: format!(
+            "composer-{}-{:08x}",
+            normalize_project_name(&name_parts.join("-")),
+            stable_hash(&name_parts.join("/")) as u32
+        ),
+        application_id,
+        compose_file: compose_file.join("/"),
+    })
+}
+
+/// Returns the compose project a compose file is managed as.
+///
+/// Applications started before composer named its projects run under docker compose's default
+/// project, the name of the directory of the compose file. While containers of that project exist
+/// it keeps being used, so they can still be listed, stopped and deleted and an upgrade doesn't
+/// start a second copy of the application next to them. Once they are removed, e.g. by a delete
+/// or a reinstall, the next `up` starts the application under its new project name. The lookup
+/// is cached for the rest of the process.
+fn get_running_compose_project(path: &str) -> anyhow::Result<ComposeProject> {
+    let mut project = get_compose_project(path)?;
+    let mut legacy_projects = LEGACY_PROJECTS.lock().unwrap();
+    let legacy_project = legacy_projects.entry(path.to_string()).or_insert_with(|| {
+        get_legacy_project_name(path).filter(|legacy_name| has_legacy_containers(path, legacy_name))
+    });
+    if let Some(legacy_name) = legacy_project {
+        debug!(
+            "Compose file {} is running under the legacy project {}",
+            path, legacy_name
+        );
+        project.name = legacy_name.clone();
+    }
+    Ok(project)
+}
+
+// Docker compose's default project name, the directory of the compose file lowercased without
+// the characters a project name can't contain
+fn get_legacy_project_name(path: &str) -> Option<String> {
+    let directory = Path::new(path).parent()?.file_name()?.to_string_lossy();
+    let name: String = directory
+        .to_lowercase()
+        .chars()
+        .filter(|c| matches!(c, 'a'..='z' | '0'..='9' | '-' | '_'))
+        .collect();
+    let name = name.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// Compose labels the containers with the directory it ran in, which tells apart the legacy
+// projects of nested compose files in identically named directories of other applications.
+// A runtime that can't list the containers is treated as having none.
+fn has_legacy_containers(path: &str, legacy_name: &str) -> bool {
+    let runtime = match app::runtime() {
+        Ok(runtime) => runtime,
+        Err(_) => return false,
+    };
+    let project_filter = format!("label={}={}", COMPOSE_PROJECT_LABEL, legacy_name);
+    let working_dir_filter = get_working_dir_filter(path);
+    captured_command(&[
+        runtime.container_command(),
+        "ps",
+        "--all",
+        "--quiet",
+        "--filter",
+        &project_filter,
+        "--filter",
+        &working_dir_filter,
+    ])
+    .map(|output| !output.trim().is_empty())
+    .unwrap_or(false)
+}
+
+fn get_working_dir_filter(path: &str) -> String {
+    let working_dir = Path::new(path)
+        .parent()
+        .map(|directory| directory.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("label={}={}", COMPOSE_WORKING_DIR_LABEL, working_dir)
+}
+
+// FNV-1a, the hash is part of project names of running containers so it must never change
+// between releases, which the hashers of std don't guarantee
+fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Compose project names may only contain lowercase letters, digits, dashes and underscores
+fn normalize_project_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('a'..='z' | '0'..='9' | '-' | '_') => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+/// Returns a compose override file adding the composer labels to every service of a compose file,
+/// so the containers of an application can be found from the container runtime alone, e.g. with
+/// `docker ps --filter label=com.bytesquid.composer.id=<id>`.
+fn get_labels_override(path: &str, project: &ComposeProject) -> Value {
+    let mut labels = Mapping::new();
+    for (label, value) in [
+        ("id", &project.application_id),
+        ("project", &project.name),
+        ("compose-file", &project.compose_file),
+    ] {
+        labels.insert(
+            Value::String(format!("{}.{}", COMPOSER_LABEL_PREFIX, label)),
+            Value::String(value.clone()),
+        );
+    }
+    let mut services = Mapping::new();
+    for service in get_compose_service_names(path) {
+        let mut service_labels = labels.clone();
+        service_labels.insert(
+            Value::String(SERVICE_LABEL.to_string()),
+            Value::String(service.clone()),
+        );
+        let mut service_override = Mapping::new();
+        service_override.insert(
+            Value::String("labels".to_string()),
+            Value::Mapping(service_labels),
+        );
+        services.insert(Value::String(service), Value::Mapping(service_override));
+    }
+    let mut compose_override = Mapping::new();
+    compose_override.insert(
+        Value::String("services".to_string()),
+        Value::Mapping(services),
+    );
+    Value::Mapping(compose_override)
+}
+
+/// Runs a command, streaming its stdout and stderr through the logger as they arrive.
+///
+/// # Returns
+///
+/// `ComposeError::CommandFailed` with everything the command wrote to stderr if it exits with a
+/// non-zero exit code, or `ComposeError::CommandNotFound` if it could not be found.
+pub fn unbuffered_command(command_line_args: &[&str]) -> Result<(), ComposeError> {
     let mut process = Command::new(command_line_args[0])
         .args(&command_line_args[1..])
         .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
+        .stderr(Stdio::piped())
         .spawn()
-        .unwrap();
+        .map_err(|e| spawn_error(command_line_args, e))?;
 
+    let stderr = process.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        stderr
+            .map(|stderr| log_subprocess_output(stderr).join("\n"))
+            .unwrap_or_default()
+    });
     if let Some(stdout) = process.stdout.take() {
         log_subprocess_output(stdout);
     }
+    let status = process.wait().map_err(|e| ComposeError::Io {
+        description: format!("Could not wait for '{}'", command_line_args.join(" ")),
+        source: e,
+    })?;
+    let stderr = stderr_reader.join().unwrap_or_default();
 
-    process.wait().unwrap().code().unwrap_or(-1)
+    if !status.success() {
+        return Err(ComposeError::CommandFailed {
+            command: command_line_args.join(" "),
+            exit_code: status.code().unwrap_or(-1),
+            stderr,
+        });
+    }
+    Ok(())
+}
+
+// Runs a command without printing its output, returning its stdout
+fn captured_command(command_line_args: &[&str]) -> Result<String, ComposeError> {
+    let output = silent_run(command_line_args)
+        .output()
+        .map_err(|e| spawn_error(command_line_args, e))?;
+    if !output.status.success() {
+        return Err(ComposeError::CommandFailed {
+            command: command_line_args.join(" "),
+            exit_code: output.status.code().unwrap_or(-1),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs `compose logs` for several compose files at once, with the output of each going straight
+/// to stdout as it arrives so that `--follow` works across all of them and the logs can be piped
+/// or redirected.
+///
+/// # Arguments
+///
+/// * `paths` - The compose files to print the logs of.
+/// * `logs_args` - Extra arguments passed to `docker compose logs`, e.g. `--tail 10`.
+///
+/// # Returns
+///
+/// The first non-zero exit code of the spawned commands, otherwise 0.
+pub fn compose_logs(paths: &[String], logs_args: &[String]) -> anyhow::Result<i32> {
+    let mut processes = Vec::new();
+    for path in paths {
+        let project = get_running_compose_project(path)?;
+        let command_line_args = app::runtime()?.compose_args(&project.name, &[path], &["logs"]);
+        trace!("[EXEC] {} {:?}", command_line_args.join(" "), logs_args);
+        let process = Command::new(command_line_args[0])
+            .args(&command_line_args[1..])
+            .args(logs_args)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| spawn_error(&command_line_args, e))?;
+        processes.push(process);
+    }
+    let mut exit_code = 0;
+    for mut process in processes {
+        let code = process.wait()?.code().unwrap_or(-1);
+        if exit_code == 0 {
+            exit_code = code;
+        }
+    }
+    Ok(exit_code)
 }
 
-fn log_subprocess_output(pipe: impl std::io::Read) {
+/// Returns the last `tail` lines of the logs of a single service of a compose file, without
+/// colours or the container name prefix.
+pub fn compose_service_logs(path: &str, service: &str, tail: usize) -> anyhow::Result<String> {
+    let project = get_running_compose_project(path)?;
+    let tail = tail.to_string();
+    Ok(captured_command(&app::runtime()?.compose_args(
+        &project.name,
+        &[path],
+        &[
+            "logs",
+            "--no-color",
+            "--no-log-prefix",
+            "--tail",
+            &tail,
+            service,
+        ],
+    ))?)
+}
+
+// Logs every line of a pipe as it arrives, returning the lines once it is closed
+fn log_subprocess_output(pipe: impl std::io::Read) -> Vec<String> {
     let reader = BufReader::new(pipe);
+    let mut lines = Vec::new();
 
     for line in reader.lines() {
         if let Ok(line) = line {
             info!("{}", line);
+            lines.push(line);
         }
     }
+    lines
 }
 
 pub fn compose_up(path: &str, application_id: &str) -> anyhow::Result<()> {
@@ -44,45 +486,53 @@ pub fn compose_up(path: &str, application_id: &str) -> anyhow::Result<()> {
         );
         return Ok(());
     }
-    trace!("[EXEC] docker compose up {}", path);
-    let exit_code =
-        unbuffered_command(&["docker", "compose", "-f", path, "up", "-d", "--remove-orphans"]);
+    let project = get_running_compose_project(path)?;
+    // The labels are passed as an override file so the rendered compose file stays untouched
+    let mut labels_file = tempfile::Builder::new()
+        .prefix("composer-labels-")
+        .suffix(".yaml")
+        .tempfile()?;
+    let labels_override = serde_yaml::to_string(&get_labels_override(path, &project))?;
+    labels_file.write_all(labels_override.as_bytes())?;
+    let labels_path = labels_file.path().to_string_lossy().to_string();
+    trace!("[EXEC] compose -p {} up {}", project.name, path);
+    let result = unbuffered_command(&app::runtime()?.compose_args(
+        &project.name,
+        &[path, &labels_path],
+        &["up", "-d", "--remove-orphans"],
+    ));
 
-    if exit_code != 0 {
-        update_application_state(application_id, ERROR)
-            .expect("Could not update application state.");
-        error!("docker compose up has failed for app {}", application_id);
-        std::process::exit(exit_code);
+    if let Err(e) = result {
+        update_application_state(application_id, ERROR)?;
+        return Err(anyhow::Error::from(e)
+            .context(format!("compose up has failed for app {}", application_id)));
     }
     Ok(())
 }
 
 // Compose files are invalid if they are empty, invalid yaml
-fn check_compose_is_valid(compose_path: &str) -> anyhow::Result<()> {
+pub fn check_compose_is_valid(compose_path: &str) -> Result<(), ComposeError> {
     // Check if the path exists
     if !Path::new(compose_path).exists() {
-        return Err(anyhow::anyhow!(
-            "The provided compose file path '{}' does not exist.",
-            compose_path
-        ));
+        return Err(ComposeError::FileNotFound(compose_path.to_string()));
     }
 
     // Read the contents of the file
-    let contents = fs::read_to_string(compose_path)?;
+    let contents = fs::read_to_string(compose_path).map_err(|e| ComposeError::Io {
+        description: format!("Could not read compose file '{}'", compose_path),
+        source: e,
+    })?;
 
     // Check if the file is empty
     if contents.trim().is_empty() {
-        return Err(anyhow::anyhow!(
-            "The provided compose file '{}' is empty.",
-            compose_path
-        ));
+        return Err(ComposeError::EmptyFile(compose_path.to_string()));
     }
 
     // Check if the file is valid YAML
-    serde_yaml::from_str::<Value>(&contents).expect(&*format!(
-        "The provided compose file '{}' is not a valid YAML file",
-        compose_path
-    ));
+    serde_yaml::from_str::<Value>(&contents).map_err(|e| ComposeError::InvalidYaml {
+        path: compose_path.to_string(),
+        source: e,
+    })?;
     Ok(())
 }
 
@@ -108,8 +558,8 @@ fn compose_has_no_services(compose_path: &str) -> bool {
     }
 }
 
-pub fn compose_down(path: &str, application_id: &str) {
-    trace!("[EXEC] docker compose down {}", path);
+pub fn compose_down(path: &str, application_id: &str) -> anyhow::Result<()> {
+    trace!("[EXEC] compose down {}", path);
     if compose_has_no_services(path) {
         // This is a valid use-case for sub-compose files
         // they should be skipped if no services are created
@@ -117,34 +567,43 @@ pub fn compose_down(path: &str, application_id: &str) {
             "Compose down for file {} has been skipped due to having no services defined.",
             path
         );
-        return;
+        return Ok(());
     }
-    let exit_code = unbuffered_command(&["docker", "compose", "-f", path, "down", "--remove-orphans"]);
+    let project = get_running_compose_project(path)?;
+    let result = unbuffered_command(&app::runtime()?.compose_args(
+        &project.name,
+        &[path],
+        &["down", "--remove-orphans"],
+    ));
 
-    if exit_code != 0 {
-        update_application_state(application_id, ERROR)
-            .expect("Could not update application state.");
+    if let Err(e) = result {
+        update_application_state(application_id, ERROR)?;
         error!(
-            "docker compose down has failed for app {}. Some containers may still persist.",
-            application_id
+            "compose down has failed for app {}. Some containers may still persist. {}",
+            application_id, e
         );
     }
+    Ok(())
 }
 
-pub fn is_compose_installed() -> bool {
-    match silent_run(&["docker", "compose", "version"]).status() {
-        Ok(status) => {
-            if status.success() {
-                true
-            } else {
-                error!("docker compose is installed but returned an error.");
-                false
-            }
-        },
-        Err(_) => {
-            false
-        }
+/// Runs a lifecycle action such as stop, start or restart over a compose file, files without
+/// services are skipped.
+pub fn compose_lifecycle(path: &str, action: &str) -> anyhow::Result<()> {
+    if compose_has_no_services(path) {
+        trace!(
+            "Compose {} for file {} has been skipped due to having no services defined.",
+            action,
+            path
+        );
+        return Ok(());
     }
+    let project = get_running_compose_project(path)?;
+    trace!("[EXEC] compose {} {}", action, path);
+    Ok(unbuffered_command(&app::runtime()?.compose_args(
+        &project.name,
+        &[path],
+        &[action],
+    ))?)
 }
 
 pub fn silent_run(args: &[&str]) -> Command {
@@ -155,15 +614,401 @@ pub fn silent_run(args: &[&str]) -> Command {
     cmd
 }
 
-pub fn compose_pull(path: &str) {
-    let command_to_run = [
-        "docker",
-        "compose",
-        "-f",
-        path,
-        "pull",
-        "--ignore-pull-failures",
-    ];
+pub fn compose_pull(path: &str) -> anyhow::Result<()> {
+    let project = get_running_compose_project(path)?;
+    let command_to_run =
+        app::runtime()?.compose_args(&project.name, &[path], &["pull", "--ignore-pull-failures"]);
     info!("Always pull is enabled. Pulling latest images. Will ignore failures of local images.");
-    unbuffered_command(&command_to_run);
+    if let Err(e) = unbuffered_command(&command_to_run) {
+        warn!(
+            "Pulling images has failed, continuing with the local images. {}",
+            e
+        );
+    }
+    Ok(())
+}
+
+/// Returns the state of every container (including stopped ones) created from a compose file.
+pub fn compose_ps(path: &str) -> anyhow::Result<Vec<ContainerStatus>> {
+    if compose_has_no_services(path) {
+        return Ok(Vec::new());
+    }
+    let project = get_running_compose_project(path)?;
+    let runtime = app::runtime()?;
+    if !runtime.supports_compose_ps_json() {
+        let mut filters = vec![format!("label={}={}", COMPOSE_PROJECT_LABEL, project.name)];
+        // Legacy projects are only named after the directory, so identically named directories
+        // of other applications are told apart by their working directory
+        if get_legacy_project_name(path).as_deref() == Some(project.name.as_str()) {
+            filters.push(get_working_dir_filter(path));
+        }
+        let mut command_line_args = vec![runtime.container_command(), "ps", "--all"];
+        for filter in &filters {
+            command_line_args.push("--filter");
+            command_line_args.push(filter);
+        }
+        command_line_args.extend_from_slice(&["--format", "{{json .}}"]);
+        let output = captured_command(&command_line_args)?;
+        return parse_labelled_containers(&output);
+    }
+    let output = captured_command(&runtime.compose_args(
+        &project.name,
+        &[path],
+        &["ps", "--all", "--format", "json"],
+    ))?;
+    parse_compose_ps(&output)
+}
+
+/// Returns the state of every container belonging to an installed application
+pub fn compose_ps_by_id(id: &str) -> anyhow::Result<Vec<ContainerStatus>> {
+    let mut containers = Vec::new();
+    for compose_file in get_compose_files_by_id(id)? {
+        containers.extend(compose_ps(&compose_file)?);
+    }
+    Ok(containers)
+}
+
+/// Returns the number of times docker has restarted a container
+pub fn container_restart_count(container_name: &str) -> anyhow::Result<u32> {
+    let output = captured_command(&[
+        app::runtime()?.container_command(),
+        "inspect",
+        "--format",
+        "{{.RestartCount}}",
+        container_name,
+    ])?;
+    Ok(output.trim().parse()?)
+}
+
+/// Returns the names of the services defined in a compose file, in the order they are defined
+pub fn get_compose_service_names(compose_path: &str) -> Vec<String> {
+    let compose: Value = match fs::read_to_string(compose_path)
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+    {
+        Some(compose) => compose,
+        None => return Vec::new(),
+    };
+    match compose.get("services") {
+        Some(Value::Mapping(services)) => services
+            .keys()
+            .filter_map(|key| key.as_str().map(|name| name.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Older compose versions print a single JSON array, newer ones print one JSON object per line
+fn parse_compose_ps(output: &str) -> anyhow::Result<Vec<ContainerStatus>> {
+    let trimmed = output.trim();
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+    if trimmed.starts_with('[') {
+        return Ok(serde_json::from_str(trimmed)?);
+    }
+    trimmed
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+// Containers listed by their labels are printed in the container command's own format,
+// docker prints the labels as `key=value,...` and the name as a string while podman prints a map
+// and an array of names
+fn parse_labelled_containers(output: &str) -> anyhow::Result<Vec<ContainerStatus>> {
+    let trimmed = output.trim();
+    let containers: Vec<serde_json::Value> = if trimmed.is_empty() {
+        Vec::new()
+    } else if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed)?
+    } else {
+        trimmed
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+    Ok(containers
+        .iter()
+        .map(get_labelled_container_status)
+        .collect())
+}
+
+fn get_labelled_container_status(container: &serde_json::Value) -> ContainerStatus {
+    let text = |key: &str| {
+        container
+            .get(key)
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let name = match container.get("Names") {
+        Some(serde_json::Value::Array(names)) => names
+            .first()
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        _ => text("Names"),
+    };
+    let label = |key: &str| match container.get("Labels") {
+        Some(serde_json::Value::Object(labels)) => labels
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string()),
+        Some(serde_json::Value::String(labels)) => labels.split(',').find_map(|label| {
+            label
+                .strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.to_string())
+        }),
+        _ => None,
+    };
+    // Containers started before composer labelled them only have the labels of compose itself
+    let service = label(SERVICE_LABEL).or_else(|| label(COMPOSE_SERVICE_LABEL));
+    // The health and exit code are only part of the human readable status,
+    // e.g. "Up 2 minutes (healthy)" or "Exited (1) 5 seconds ago"
+    let status = text("Status");
+    let health = if status.contains("(healthy)") {
+        "healthy"
+    } else if status.contains("(unhealthy)") {
+        "unhealthy"
+    } else if status.contains("(health: starting)") || status.contains("(starting)") {
+        "starting"
+    } else {
+        ""
+    };
+    let exit_code = container
+        .get("ExitCode")
+        .and_then(|exit_code| exit_code.as_i64())
+        .or_else(|| {
+            status
+                .strip_prefix("Exited (")
+                .and_then(|rest| rest.split(')').next())
+                .and_then(|exit_code| exit_code.parse().ok())
+        })
+        .unwrap_or(0) as i32;
+    ContainerStatus {
+        name,
+        service: service.unwrap_or_default(),
+        image: text("Image"),
+        state: text("State").to_lowercase(),
+        health: health.to_string(),
+        exit_code,
+        publishers: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compose_ps_json_lines() -> anyhow::Result<()> {
+        let output = r#"{"Name":"app-web-1","Service":"web","Image":"nginx","State":"running","Health":"healthy","ExitCode":0,"Publishers":[{"URL":"0.0.0.0","TargetPort":80,"PublishedPort":8080,"Protocol":"tcp"}]}
+{"Name":"app-db-1","Service":"db","Image":"postgres","State":"exited","Health":"","ExitCode":1,"Publishers":null}
+"#;
+        let containers = parse_compose_ps(output)?;
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].service, "web");
+        assert_eq!(containers[0].health, "healthy");
+        assert_eq!(
+            containers[0].publishers.as_ref().unwrap()[0].published_port,
+            8080
+        );
+        assert_eq!(containers[1].state, "exited");
+        assert_eq!(containers[1].exit_code, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compose_ps_json_array() -> anyhow::Result<()> {
+        let output = r#"[{"Name":"app-web-1","Service":"web","State":"restarting"}]"#;
+        let containers = parse_compose_ps(output)?;
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].state, "restarting");
+        assert_eq!(containers[0].health, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_compose_service_names() -> anyhow::Result<()> {
+        let compose = tempfile::NamedTempFile::new()?;
+        fs::write(
+            compose.path(),
+            "services:\n  web:\n    image: nginx\n  db:\n    image: postgres\n",
+        )?;
+        let names = get_compose_service_names(compose.path().to_str().unwrap());
+        assert_eq!(names, vec!["web".to_string(), "db".to_string()]);
+        assert!(get_compose_service_names("does_not_exist.yaml").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_labelled_containers() -> anyhow::Result<()> {
+        // docker ps --format '{{json .}}'
+        let docker_output = r#"{"Names":"composer-app-web-1","Image":"nginx","State":"running","Status":"Up 2 minutes (healthy)","Labels":"com.bytesquid.composer.id=app,com.bytesquid.composer.service=web"}
+{"Names":"composer-app-db-1","Image":"postgres","State":"exited","Status":"Exited (3) 5 seconds ago","Labels":"com.bytesquid.composer.service=db"}
+"#;
+        let containers = parse_labelled_containers(docker_output)?;
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].name, "composer-app-web-1");
+        assert_eq!(containers[0].service, "web");
+        assert_eq!(containers[0].health, "healthy");
+        assert_eq!(containers[1].service, "db");
+        assert_eq!(containers[1].state, "exited");
+        assert_eq!(containers[1].exit_code, 3);
+        // podman ps --format json
+        let podman_output = r#"[{"Names":["composer-app-web-1"],"Image":"nginx","State":"running","Status":"Up 5 seconds (starting)","ExitCode":0,"Labels":{"com.bytesquid.composer.service":"web"}}]"#;
+        let containers = parse_labelled_containers(podman_output)?;
+        assert_eq!(containers[0].name, "composer-app-web-1");
+        assert_eq!(containers[0].service, "web");
+        assert_eq!(containers[0].health, "starting");
+        // Containers without composer labels fall back to the service label of compose
+        let legacy_output = r#"{"Names":"app-web-1","Image":"nginx","State":"running","Status":"Up 1 minute","Labels":"com.docker.compose.project=app,com.docker.compose.service=web"}"#;
+        let containers = parse_labelled_containers(legacy_output)?;
+        assert_eq!(containers[0].service, "web");
+        assert!(parse_labelled_containers("\n")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_compose_ps_empty() -> anyhow::Result<()> {
+        assert!(parse_compose_ps("\n")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_compose_is_valid() -> anyhow::Result<()> {
+        let compose = tempfile::NamedTempFile::new()?;
+        let compose_path = compose.path().to_str().unwrap();
+        fs::write(compose.path(), "  \n")?;
+        let empty = check_compose_is_valid(compose_path);
+        fs::write(compose.path(), "services: [\n")?;
+        let invalid = check_compose_is_valid(compose_path);
+        fs::write(compose.path(), "services: {}\n")?;
+        let valid = check_compose_is_valid(compose_path);
+        assert!(matches!(empty, Err(ComposeError::EmptyFile(_))));
+        assert!(matches!(invalid, Err(ComposeError::InvalidYaml { .. })));
+        assert!(valid.is_ok());
+        assert!(matches!(
+            check_compose_is_valid("does_not_exist.yaml"),
+            Err(ComposeError::FileNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unbuffered_command_errors() {
+        let not_found = unbuffered_command(&["composer-command-that-does-not-exist"]);
+        assert!(matches!(not_found, Err(ComposeError::CommandNotFound(_))));
+        let failed = unbuffered_command(&["sh", "-c", "echo failed >&2; exit 3"]).unwrap_err();
+        assert!(matches!(
+            &failed,
+            ComposeError::CommandFailed { exit_code: 3, stderr, .. } if stderr == "failed"
+        ));
+        assert_eq!(failed.exit_code(), 3);
+        assert!(unbuffered_command(&["sh", "-c", "exit 0"]).is_ok());
+    }
+
+    #[test]
+    fn test_get_compose_project() -> anyhow::Result<()> {
+        let application_directory = get_composer_directory()?.join("My.App");
+        let root = get_compose_project(
+            application_directory
+                .join("docker-compose.jinja2")
+                .to_str()
+                .unwrap(),
+        )?;
+        assert_eq!(root.application_id, "My.App");
+        assert_eq!(root.compose_file, "docker-compose.jinja2");
+        assert_eq!(root.name, "composer-my-app-a2e49d62");
+        let nested = get_compose_project(
+            application_directory
+                .join("services/db/docker-compose.jinja2")
+                .to_str()
+                .unwrap(),
+        )?;
+        assert_eq!(nested.compose_file, "services/db/docker-compose.jinja2");
+        assert_eq!(nested.name, "composer-my-app-services-db-d9da0b8e");
+        // Files outside of the composer directory don't belong to an application
+        assert!(get_compose_project("/tmp/docker-compose.jinja2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_legacy_project_name() {
+        trace!("Running test_get_legacy_project_name.");
+        assert_eq!(
+            get_legacy_project_name("/root/.composer/My.App/docker-compose.jinja2"),
+            Some("myapp".to_string())
+        );
+        assert_eq!(
+            get_legacy_project_name("/root/.composer/my_app/services/db/docker-compose.jinja2"),
+            Some("db".to_string())
+        );
+        assert_eq!(
+            get_legacy_project_name("/root/.composer/_app-1/docker-compose.jinja2"),
+            Some("app-1".to_string())
+        );
+        assert_eq!(
+            get_legacy_project_name("/root/.composer/.../docker-compose.jinja2"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_compose_project_names_dont_collide() -> anyhow::Result<()> {
+        trace!("Running test_get_compose_project_names_dont_collide.");
+        let composer_directory = get_composer_directory()?;
+        let project_name = |path: &str| -> anyhow::Result<String> {
+            let compose_file = composer_directory.join(path).join("docker-compose.jinja2");
+            Ok(get_compose_project(compose_file.to_str().unwrap())?.name)
+        };
+        // Each pair normalizes to the same readable name
+        for (first, second) in [
+            ("my.app", "my-app"),
+            ("svc/db", "svc-db"),
+            ("app/a/b", "app/a-b"),
+        ] {
+            let first_name = project_name(first)?;
+            let second_name = project_name(second)?;
+            assert_ne!(first_name, second_name);
+            assert_eq!(
+                first_name[..first_name.len() - 8],
+                second_name[..second_name.len() - 8]
+            );
+        }
+        // The same compose file always gets the same project
+        assert_eq!(project_name("my.app")?, project_name("my.app")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_labels_override() -> anyhow::Result<()> {
+        let compose = tempfile::NamedTempFile::new()?;
+        fs::write(
+            compose.path(),
+            "services:\n  web:\n    image: nginx\n    labels:\n      team: web\n",
+        )?;
+        let project = ComposeProject {
+            application_id: "my_app".to_string(),
+            compose_file: "docker-compose.jinja2".to_string(),
+            name: "composer-my_app".to_string(),
+        };
+        let compose_override = get_labels_override(compose.path().to_str().unwrap(), &project);
+        let labels = &compose_override["services"]["web"]["labels"];
+        assert_eq!(labels["com.bytesquid.composer.id"], Value::from("my_app"));
+        assert_eq!(
+            labels["com.bytesquid.composer.project"],
+            Value::from("composer-my_app")
+        );
+        assert_eq!(
+            labels["com.bytesquid.composer.compose-file"],
+            Value::from("docker-compose.jinja2")
+        );
+        assert_eq!(labels["com.bytesquid.composer.service"], Value::from("web"));
+        Ok(())
+    }
 }
@@ -1,23 +1,180 @@
 use serde_yaml::{Mapping, Value};
 
-use crate::utils::yaml_string_parser::parse_yaml_string;
-use anyhow::Context;
+use crate::utils::yaml_string_parser::{
+    read_yaml_string_file, set_yaml_string, set_yaml_string_as_json, set_yaml_string_as_string,
+};
+use anyhow::{anyhow, Context};
+use clap::Args;
+use serde::{Deserialize, Serialize};
 use serde_yaml::mapping::Entry;
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
 
-fn merge_maps(existing_map: &mut Mapping, new_map: Mapping) {
+/// How two lists defined for the same key are merged when layering values.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ListMergeStrategy {
+    /// The later list is appended to the earlier one
+    #[default]
+    Append,
+    /// The later list replaces the earlier one, like Helm
+    Replace,
+    /// Maps in the later list are merged into the map of the earlier list with the same value for
+    /// the given field, e.g. `name`, any other items are appended
+    MergeByKey(String),
+}
+
+/// The field lists of maps are merged by if `merge-by-key` doesn't name one
+const DEFAULT_MERGE_KEY: &str = "name";
+
+impl ListMergeStrategy {
+    pub fn is_default(&self) -> bool {
+        *self == ListMergeStrategy::default()
+    }
+}
+
+impl FromStr for ListMergeStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy.split_once(':') {
+            None if strategy == "append" => Ok(ListMergeStrategy::Append),
+            None if strategy == "replace" => Ok(ListMergeStrategy::Replace),
+            None if strategy == "merge-by-key" => Ok(ListMergeStrategy::MergeByKey(
+                DEFAULT_MERGE_KEY.to_string(),
+            )),
+            Some(("merge-by-key", field)) if !field.is_empty() => {
+                Ok(ListMergeStrategy::MergeByKey(field.to_string()))
+            }
+            _ => Err(anyhow!(
+                "Unknown list merge strategy '{}', must be one of append, replace, merge-by-key or merge-by-key:<field>",
+                strategy
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ListMergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListMergeStrategy::Append => write!(f, "append"),
+            ListMergeStrategy::Replace => write!(f, "replace"),
+            ListMergeStrategy::MergeByKey(field) => write!(f, "merge-by-key:{}", field),
+        }
+    }
+}
+
+impl TryFrom<String> for ListMergeStrategy {
+    type Error = anyhow::Error;
+
+    fn try_from(strategy: String) -> Result<Self, Self::Error> {
+        strategy.parse()
+    }
+}
+
+impl From<ListMergeStrategy> for String {
+    fn from(strategy: ListMergeStrategy) -> Self {
+        strategy.to_string()
+    }
+}
+
+/// The list merge strategy of every key path, the default unless a values file annotates the key path
+#[derive(Debug, Default)]
+struct ListMergeStrategies {
+    default: ListMergeStrategy,
+    by_key_path: HashMap<String, ListMergeStrategy>,
+}
+
+impl ListMergeStrategies {
+    fn get(&self, key_path: &str) -> &ListMergeStrategy {
+        self.by_key_path.get(key_path).unwrap_or(&self.default)
+    }
+}
+
+/// The reserved keys of a values file annotating how its lists are merged, i.e. `composer.merge`
+const COMPOSER_KEY: &str = "composer";
+const MERGE_ANNOTATION_KEY: &str = "merge";
+
+// Removes the composer.merge annotations from a values file and adds them to the strategies, so
+// they apply to this and every later values file
+fn take_merge_annotations(
+    values: &mut Mapping,
+    strategies: &mut ListMergeStrategies,
+) -> anyhow::Result<()> {
+    let (annotations, composer_is_empty) = match values.get_mut(COMPOSER_KEY) {
+        Some(Value::Mapping(composer)) => match composer.remove(MERGE_ANNOTATION_KEY) {
+            Some(Value::Mapping(annotations)) => (annotations, composer.is_empty()),
+            Some(_) => {
+                return Err(anyhow!(
+                    "composer.merge must be a mapping of key paths to list merge strategies."
+                ))
+            }
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    if composer_is_empty {
+        values.remove(COMPOSER_KEY);
+    }
+    for (key_path, strategy) in annotations {
+        match (key_path.as_str(), strategy.as_str()) {
+            (Some(key_path), Some(strategy)) => {
+                let strategy = strategy
+                    .parse()
+                    .with_context(|| format!("Invalid list merge strategy for {}", key_path))?;
+                strategies
+                    .by_key_path
+                    .insert(key_path.to_string(), strategy);
+            }
+            _ => {
+                return Err(anyhow!(
+                    "composer.merge must be a mapping of key paths to list merge strategies."
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+// Returns the key path of a child key, escaping dots in the key as they are in --set key paths
+fn child_key_path(key_path: &str, key: &Value) -> Option<String> {
+    let key = match key {
+        Value::String(key) => key.clone(),
+        Value::Number(key) => key.to_string(),
+        Value::Bool(key) => key.to_string(),
+        _ => return None,
+    };
+    let key = key.replace('\\', "\\\\").replace('.', "\\.");
+    if key_path.is_empty() {
+        Some(key)
+    } else {
+        Some(format!("{}.{}", key_path, key))
+    }
+}
+
+fn merge_maps(
+    existing_map: &mut Mapping,
+    new_map: Mapping,
+    strategies: &ListMergeStrategies,
+    key_path: &str,
+) {
     for (new_key, new_value) in new_map {
-        let new_value_clone = new_value.clone();
+        let child_path = child_key_path(key_path, &new_key).unwrap_or_default();
         match existing_map.entry(new_key) {
-            Entry::Occupied(mut entry) => match (entry.get_mut(), &new_value) {
+            Entry::Occupied(mut entry) => match (entry.get_mut(), new_value) {
                 (Value::Mapping(existing_inner), Value::Mapping(new_inner)) => {
-                    merge_maps(existing_inner, new_inner.clone());
+                    merge_maps(existing_inner, new_inner, strategies, &child_path);
                 }
                 (Value::Sequence(existing_list), Value::Sequence(new_list)) => {
-                    existing_list.extend(new_list.clone());
+                    merge_lists(existing_list, new_list, strategies, &child_path);
                 }
-                _ => {
-                    entry.insert(new_value_clone);
+                (_, new_value) => {
+                    entry.insert(new_value);
                 }
             },
             Entry::Vacant(entry) => {
@@ -27,17 +184,48 @@ fn merge_maps(existing_map: &mut Mapping, new_map: Mapping) {
     }
 }
 
-/// Loads one or more YAML files or key-value string(s) into a single `serde_yaml::Value` object.
+// Lists inside merged list items are looked up by the key path of the list they are in, without an index
+fn merge_lists(
+    existing_list: &mut Vec<Value>,
+    new_list: Vec<Value>,
+    strategies: &ListMergeStrategies,
+    key_path: &str,
+) {
+    match strategies.get(key_path) {
+        ListMergeStrategy::Append => existing_list.extend(new_list),
+        ListMergeStrategy::Replace => *existing_list = new_list,
+        ListMergeStrategy::MergeByKey(field) => {
+            for new_item in new_list {
+                let existing_item = new_item.get(field).and_then(|id| {
+                    existing_list
+                        .iter_mut()
+                        .find(|existing_item| existing_item.get(field) == Some(id))
+                });
+                match (existing_item, new_item) {
+                    (Some(Value::Mapping(existing_item)), Value::Mapping(new_item)) => {
+                        merge_maps(existing_item, new_item, strategies, key_path);
+                    }
+                    (_, new_item) => existing_list.push(new_item),
+                }
+            }
+        }
+    }
+}
+
+/// Loads one or more YAML files into a single `serde_yaml::Value` object.
 ///
-/// This function takes a vector of YAML file paths or key-value strings in the format of "x.y.z=foo", and
-/// loads each one into a `serde_yaml::Value` object. If a key-value string is provided, it is parsed into
-/// a YAML mapping using the `parse_yaml_string` function. If a file path is provided, the file is read and
-/// deserialized into a YAML mapping using the `read_yaml_file` function. The resulting mappings are then merged
-/// into a single mapping, with any conflicting values being overwritten by the last value encountered.
+/// This function takes a vector of YAML file paths, and loads each one into a `serde_yaml::Value` object
+/// using the `read_yaml_file` function. The resulting mappings are then merged into a single mapping, with
+/// any conflicting values being overwritten by the last value encountered. Single values are set with
+/// `ValueOptions` instead.
+///
+/// Lists defined in more than one file are merged with the given strategy, unless a file annotates the key
+/// path of the list with its own strategy under `composer.merge`, which applies to that file and every later
+/// file. The annotations are removed from the returned values.
 ///
 /// # Errors
 ///
-/// This function returns an `anyhow::Error` if any of the input files or strings cannot be loaded or parsed.
+/// This function returns an `anyhow::Error` if any of the input files cannot be loaded or parsed.
 ///
 /// # Examples
 ///
@@ -49,13 +237,11 @@ fn merge_maps(existing_map: &mut Mapping, new_map: Mapping) {
 ///     let yaml_files = vec![
 ///         "examples/values1.yaml",
 ///         "examples/values2.yaml",
-///         "abc.def.ghi=jkl",
 ///     ];
 ///
-///     let yaml_value = load_yaml_files(&yaml_files)?;
+///     let yaml_value = load_yaml_files(&yaml_files, &ListMergeStrategy::Replace)?;
 ///
 ///     assert_eq!(yaml_value["foo"]["bar"], Value::String("baz".to_owned()));
-///     assert_eq!(yaml_value["abc"]["def"]["ghi"], Value::String("jkl".to_owned()));
 ///
 ///     Ok(())
 /// }
@@ -63,65 +249,521 @@ fn merge_maps(existing_map: &mut Mapping, new_map: Mapping) {
 ///
 /// # Arguments
 ///
-/// * `yaml_files` - A vector of YAML file paths or key-value strings in the format of "x.y.z=foo".
+/// * `yaml_files` - A vector of YAML file paths.
+/// * `list_merge` - How lists are merged unless a values file annotates them.
 ///
 /// # Returns
 ///
-/// A `serde_yaml::Value` object representing the merged YAML mappings loaded from the input files or strings.
-pub fn load_yaml_files(yaml_files: &Vec<&str>) -> anyhow::Result<Value> {
-    let mut yaml_values = Mapping::new();
+/// A `serde_yaml::Value` object representing the merged YAML mappings loaded from the input files.
+#[cfg(test)]
+pub fn load_yaml_files(
+    yaml_files: &Vec<&str>,
+    list_merge: &ListMergeStrategy,
+) -> anyhow::Result<Value> {
+    merge_values_files(&read_values_files(yaml_files)?, list_merge).map(|(values, _)| values)
+}
+
+// The documents of a values file, read once so they can be merged into more than one set of values
+#[derive(Debug)]
+struct ValuesFile {
+    path: String,
+    documents: Vec<Value>,
+}
+
+fn read_values_files(yaml_files: &[&str]) -> anyhow::Result<Vec<ValuesFile>> {
+    if yaml_files
+        .iter()
+        .filter(|yaml_file| **yaml_file == STDIN_VALUE_FILE)
+        .count()
+        > 1
+    {
+        return Err(anyhow!("Values can only be read from stdin once."));
+    }
 
+    let mut values_files = Vec::new();
     for yaml_file in yaml_files {
-        let yaml = if yaml_file.contains("=") {
-            parse_yaml_string(yaml_file)?
-        } else {
-            read_yaml_file(yaml_file)
-                .with_context(|| format!("Failed to read values YAML file: {}", yaml_file))?
-        };
+        if yaml_file.contains('=') && !Path::new(yaml_file).exists() {
+            return Err(anyhow!(
+                "Values YAML file {} does not exist, use --set {} to set a single value.",
+                yaml_file,
+                yaml_file
+            ));
+        }
+        let documents = read_yaml_documents(yaml_file)
+            .with_context(|| format!("Failed to read values YAML file: {}", yaml_file))?;
+        values_files.push(ValuesFile {
+            path: yaml_file.to_string(),
+            documents,
+        });
+    }
+    Ok(values_files)
+}
 
-        // Start merging here, whether it's a map or not
-        match &yaml {
-            Value::Mapping(map) => {
-                for (key, value) in map {
-                    match yaml_values.entry(key.clone()) {
-                        Entry::Occupied(mut entry) => match (entry.get_mut(), value) {
-                            (Value::Mapping(existing_inner), Value::Mapping(new_inner)) => {
-                                merge_maps(existing_inner, new_inner.clone());
-                            }
-                            (Value::Sequence(existing_list), Value::Sequence(new_list)) => {
-                                existing_list.extend(new_list.clone());
-                            }
-                            _ => {
-                                entry.insert(value.clone());
-                            }
-                        },
-                        Entry::Vacant(entry) => {
-                            entry.insert(value.clone());
-                        }
-                    }
+// Merges values files like `load_yaml_files`, also returning the list merge strategies the files
+// annotated
+fn merge_values_files<'a>(
+    values_files: impl IntoIterator<Item = &'a ValuesFile>,
+    list_merge: &ListMergeStrategy,
+) -> anyhow::Result<(Value, ListMergeStrategies)> {
+    let mut yaml_values = Mapping::new();
+    let mut strategies = ListMergeStrategies {
+        default: list_merge.clone(),
+        ..Default::default()
+    };
+
+    for values_file in values_files {
+        // Each document of a multi-document file is merged in order, as if it was its own file
+        for yaml in &values_file.documents {
+            match yaml.clone() {
+                Value::Mapping(mut map) => {
+                    take_merge_annotations(&mut map, &mut strategies).with_context(|| {
+                        format!("Failed to read values YAML file: {}", values_file.path)
+                    })?;
+                    merge_maps(&mut yaml_values, map, &strategies, "");
+                }
+                // In case top-level structure is not a map
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Expected top-level YAML structure to be a mapping."
+                    ));
                 }
             }
-            // In case top-level structure is not a map
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Expected top-level YAML structure to be a mapping."
-                ));
+        }
+    }
+
+    Ok((Value::Mapping(yaml_values), strategies))
+}
+
+// Adds the list merge strategies annotated by value files back as composer.merge annotations
+fn put_merge_annotations(values: &mut Mapping, strategies: &ListMergeStrategies) {
+    if strategies.by_key_path.is_empty() {
+        return;
+    }
+    let annotations: BTreeMap<&String, String> = strategies
+        .by_key_path
+        .iter()
+        .map(|(key_path, strategy)| (key_path, strategy.to_string()))
+        .collect();
+    let annotations = annotations
+        .into_iter()
+        .map(|(key_path, strategy)| (Value::from(key_path.as_str()), Value::from(strategy)))
+        .collect();
+    let composer = values
+        .entry(Value::from(COMPOSER_KEY))
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    if let Value::Mapping(composer) = composer {
+        composer.insert(
+            Value::from(MERGE_ANNOTATION_KEY),
+            Value::Mapping(annotations),
+        );
+    }
+}
+
+/// Every source of values for rendering an application, shared by each command that renders
+/// templates and stored with an installed application so upgrades can reuse them.
+///
+/// Sources are applied in order of precedence from lowest to highest: value files in the order
+/// given, then `COMPOSER_VALUE_` environment variables, `--set-json`, `--set`, `--set-string` and
+/// `--set-file`. Environment variables are read every time values are loaded and never stored.
+#[derive(Debug, Args, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, from = "StoredValueOptions")]
+pub struct ValueOptions {
+    /// The value files to use, later files take precedence
+    #[clap(short, long)]
+    pub value_files: Vec<String>,
+    /// Sets a value with a key path, e.g. --set app.replicas=3. The value is parsed as a YAML
+    ///   scalar so numbers, booleans and null keep their type
+    #[clap(long)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub set: Vec<String>,
+    /// Sets a value with a key path, e.g. --set-string app.version=1.10. The value is always
+    ///   a string
+    #[clap(long)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub set_string: Vec<String>,
+    /// Sets a value with a key path to the contents of a file, e.g. --set-file tls.cert=cert.pem
+    #[clap(long)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub set_file: Vec<String>,
+    /// Sets a value with a key path to a JSON value, e.g. --set-json 'ports=[{"target": 80}]'
+    #[clap(long)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub set_json: Vec<String>,
+    /// How lists defined in more than one value file are merged: append, replace or
+    ///   merge-by-key[:<field>] to merge lists of maps by a field, name by default
+    #[clap(long, default_value_t)]
+    #[serde(skip_serializing_if = "ListMergeStrategy::is_default")]
+    pub list_merge: ListMergeStrategy,
+}
+
+// ValueOptions as stored in config.json and revision.json. Older versions stored key-value
+// strings amongst the value files, so they are moved to --set when loaded.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StoredValueOptions {
+    value_files: Vec<String>,
+    set: Vec<String>,
+    set_string: Vec<String>,
+    set_file: Vec<String>,
+    set_json: Vec<String>,
+    list_merge: ListMergeStrategy,
+}
+
+impl From<StoredValueOptions> for ValueOptions {
+    fn from(stored: StoredValueOptions) -> Self {
+        let (legacy_sets, value_files): (Vec<String>, Vec<String>) = stored
+            .value_files
+            .into_iter()
+            .partition(|value_file| value_file.contains('=') && !Path::new(value_file).exists());
+        ValueOptions {
+            value_files,
+            set: legacy_sets.into_iter().chain(stored.set).collect(),
+            set_string: stored.set_string,
+            set_file: stored.set_file,
+            set_json: stored.set_json,
+            list_merge: stored.list_merge,
+        }
+    }
+}
+
+impl ValueOptions {
+    #[cfg(test)]
+    pub fn from_value_files(value_files: Vec<String>) -> Self {
+        ValueOptions {
+            value_files,
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if no values have been given at all
+    pub fn is_empty(&self) -> bool {
+        self.value_files.is_empty()
+            && self.set.is_empty()
+            && self.set_string.is_empty()
+            && self.set_file.is_empty()
+            && self.set_json.is_empty()
+    }
+
+    /// Returns a copy of these options with the template directory's values.yaml as the lowest
+    /// precedence value file, see `with_default_values`.
+    pub fn with_default_values(&self, directory: &Path) -> Self {
+        ValueOptions {
+            value_files: with_default_values(directory, &self.value_files),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of these options to store with an application. Values read from stdin can't
+    /// be read again, so they are left out, they are still part of the values snapshot an upgrade
+    /// reuses.
+    pub fn to_stored(&self) -> Self {
+        if !self.value_files.iter().any(|file| file == STDIN_VALUE_FILE) {
+            return self.clone();
+        }
+        ValueOptions {
+            value_files: self
+                .value_files
+                .iter()
+                .filter(|file| *file != STDIN_VALUE_FILE)
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns these options with the given overrides layered on top, the value files and set
+    /// values of the overrides are applied after these. The list merge strategy of the overrides
+    /// is used unless it is the default.
+    pub fn layered_with(&self, overrides: &ValueOptions) -> Self {
+        let concat = |own: &Vec<String>, other: &Vec<String>| -> Vec<String> {
+            own.iter().chain(other).cloned().collect()
+        };
+        ValueOptions {
+            value_files: concat(&self.value_files, &overrides.value_files),
+            set: concat(&self.set, &overrides.set),
+            set_string: concat(&self.set_string, &overrides.set_string),
+            set_file: concat(&self.set_file, &overrides.set_file),
+            set_json: concat(&self.set_json, &overrides.set_json),
+            list_merge: if overrides.list_merge.is_default() {
+                self.list_merge.clone()
+            } else {
+                overrides.list_merge.clone()
+            },
+        }
+    }
+
+    /// Reads every value source once, see `ValueLayers`.
+    pub fn read(&self) -> anyhow::Result<ValueLayers> {
+        Ok(ValueLayers {
+            default_values_files: 0,
+            values_files: read_values_files(&get_value_files_as_refs(&self.value_files))?,
+            set_file: self
+                .set_file
+                .iter()
+                .map(|set_file| read_yaml_string_file(set_file))
+                .collect::<anyhow::Result<_>>()?,
+            options: self.clone(),
+        })
+    }
+
+    /// Reads every value source once with the template directory's values.yaml as the lowest
+    /// precedence value file, see `with_default_values`. Values reused by an upgrade are layered
+    /// over the default values, see `ReusedValues`.
+    pub fn read_with_default_values(
+        &self,
+        directory: &Path,
+        reused: Option<&ReusedValues>,
+    ) -> anyhow::Result<ValueLayers> {
+        let options = match reused {
+            Some(reused) => reused.layered_with(self),
+            None => self.clone(),
+        };
+        let with_default_values = options.with_default_values(directory);
+        let mut layers = with_default_values.read()?;
+        layers.default_values_files =
+            with_default_values.value_files.len() - options.value_files.len();
+        if let Some(reused) = reused {
+            layers.values_files.insert(
+                layers.default_values_files,
+                ValuesFile {
+                    path: "the values of the previous revision".to_string(),
+                    documents: vec![reused.snapshot.clone()],
+                },
+            );
+        }
+        Ok(layers)
+    }
+
+    /// Loads every value source into a single consolidated `serde_yaml::Value`.
+    pub fn load(&self) -> anyhow::Result<Value> {
+        self.read()?.values()
+    }
+
+    /// Loads every value source except `COMPOSER_VALUE_` environment variables, which are often
+    /// secrets, so the result is safe to store on disk.
+    pub fn load_without_environment(&self) -> anyhow::Result<Value> {
+        self.read()?.values_without_environment()
+    }
+}
+
+/// The values an upgrade reuses from the previous revision.
+///
+/// The user values snapshot of the previous revision is layered between the template's default
+/// values and the given value files, so edited or deleted value files don't change it. The set
+/// values of the previous options are applied again with the given set values, so they keep their
+/// precedence over value files and `COMPOSER_VALUE_` environment variables. `--set-file` values
+/// are only taken from the snapshot, as their files may have changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReusedValues {
+    /// The user values snapshot of the previous revision, see `ValuesSnapshot`
+    pub snapshot: Value,
+    /// The value options stored with the previous revision
+    pub options: ValueOptions,
+}
+
+impl ReusedValues {
+    /// Returns the value options to store with an upgrade that reuses these values, the previous
+    /// options with the given options layered on top.
+    pub fn stored_with(&self, values: &ValueOptions) -> ValueOptions {
+        self.options.layered_with(values)
+    }
+
+    // Returns the options to load over the snapshot
+    fn layered_with(&self, values: &ValueOptions) -> ValueOptions {
+        ValueOptions {
+            set: self.options.set.clone(),
+            set_string: self.options.set_string.clone(),
+            set_json: self.options.set_json.clone(),
+            list_merge: self.options.list_merge.clone(),
+            ..Default::default()
+        }
+        .layered_with(values)
+    }
+}
+
+/// Every value source of `ValueOptions`, read once. An application is rendered with, stored with
+/// and snapshotted with different sets of values built from the same layers, so a value file that
+/// can only be read once such as a pipe, or that is edited in the meantime, is the same in each.
+#[derive(Debug)]
+pub struct ValueLayers {
+    // The number of value files that are the template's default values
+    default_values_files: usize,
+    values_files: Vec<ValuesFile>,
+    // The --set-file values with the contents of their files, see `read_yaml_string_file`
+    set_file: Vec<String>,
+    options: ValueOptions,
+}
+
+impl ValueLayers {
+    /// Consolidates every layer, the values to render with.
+    pub fn values(&self) -> anyhow::Result<Value> {
+        self.consolidate(true, true, false)
+    }
+
+    /// Consolidates every layer except `COMPOSER_VALUE_` environment variables, which are often
+    /// secrets, so the result is safe to store on disk.
+    pub fn values_without_environment(&self) -> anyhow::Result<Value> {
+        self.consolidate(true, false, false)
+    }
+
+    /// Consolidates only the values given by the user, without the template's default values or
+    /// environment variables. The `composer.merge` annotations of the value files are kept, so
+    /// value files layered over the result are merged the same way they would be if they were
+    /// layered over the original value files.
+    pub fn user_values(&self) -> anyhow::Result<Value> {
+        self.consolidate(false, false, true)
+    }
+
+    fn consolidate(
+        &self,
+        with_default_values: bool,
+        with_environment: bool,
+        with_annotations: bool,
+    ) -> anyhow::Result<Value> {
+        let skipped = if with_default_values {
+            0
+        } else {
+            self.default_values_files
+        };
+        let (mut values, strategies) = merge_values_files(
+            self.values_files.iter().skip(skipped),
+            &self.options.list_merge,
+        )?;
+        if let Value::Mapping(values_map) = &mut values {
+            if with_annotations {
+                put_merge_annotations(values_map, &strategies);
+            }
+            if with_environment {
+                load_environment_values(values_map, std::env::vars_os())?;
+            }
+            for set_json in &self.options.set_json {
+                set_yaml_string_as_json(values_map, set_json)?;
+            }
+            for set in &self.options.set {
+                set_yaml_string(values_map, set)?;
+            }
+            for set_string in &self.options.set_string {
+                set_yaml_string_as_string(values_map, set_string)?;
+            }
+            for set_file in &self.set_file {
+                set_yaml_string_as_string(values_map, set_file)?;
             }
         }
+        Ok(values)
+    }
+}
+
+/// The prefix of environment variables that set values, e.g. `COMPOSER_VALUE_database__password`
+pub const ENVIRONMENT_VALUE_PREFIX: &str = "COMPOSER_VALUE_";
+
+/// Sets a value for every environment variable starting with `COMPOSER_VALUE_`. The rest of the
+/// name is the key path with `__` separating keys, e.g. `COMPOSER_VALUE_database__password` sets
+/// `database.password`. Values are always strings, so secrets such as `0123` are kept as they are.
+///
+/// Variables are applied in order of name so the result doesn't depend on the environment's order.
+/// Values are never logged or included in errors, as they are often secrets.
+fn load_environment_values(
+    values: &mut Mapping,
+    variables: impl IntoIterator<Item = (OsString, OsString)>,
+) -> anyhow::Result<()> {
+    let mut variables: Vec<(String, String)> = variables
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with(ENVIRONMENT_VALUE_PREFIX))
+        .collect();
+    variables.sort();
+    for (name, value) in variables {
+        let key_path = name[ENVIRONMENT_VALUE_PREFIX.len()..].replace("__", ".");
+        trace!("Setting {} from environment variable {}", key_path, name);
+        set_yaml_string_as_string(values, &format!("{}={}", key_path, value)).map_err(|_| {
+            anyhow!(
+                "Environment variable {} does not map to a valid key path, use __ to separate keys, e.g. {}database__password",
+                name,
+                ENVIRONMENT_VALUE_PREFIX
+            )
+        })?;
     }
+    Ok(())
+}
+
+/// The values file a template directory can ship with, loaded as the lowest precedence layer
+pub const DEFAULT_VALUES_FILE: &str = "values.yaml";
 
-    Ok(Value::Mapping(yaml_values))
+/// Prepends a template directory's own values.yaml to the given value files, so the template's
+/// defaults are the lowest precedence layer and every user value file overrides them.
+///
+/// The default values file is skipped if the template doesn't have one or if it is already one
+/// of the given value files.
+///
+/// # Arguments
+///
+/// * `directory` - The template directory, containing app.yaml.
+/// * `value_files` - The value files given by the user.
+///
+/// # Returns
+///
+/// The value files to load, in order of precedence from lowest to highest.
+pub fn with_default_values(directory: &Path, value_files: &[String]) -> Vec<String> {
+    let default_values = directory.join(DEFAULT_VALUES_FILE);
+    let mut all_value_files = Vec::new();
+    if let Ok(default_canonical) = default_values.canonicalize() {
+        let already_given = value_files.iter().any(|value_file| {
+            Path::new(value_file)
+                .canonicalize()
+                .map(|canonical| canonical == default_canonical)
+                .unwrap_or(false)
+        });
+        if !already_given {
+            trace!("Using default values from {}", default_values.display());
+            all_value_files.push(default_values.to_string_lossy().to_string());
+        }
+    }
+    all_value_files.extend(value_files.iter().cloned());
+    all_value_files
 }
 
 pub fn get_value_files_as_refs(strings: &Vec<String>) -> Vec<&str> {
     strings.iter().map(|s| s.as_ref()).collect()
 }
 
-pub fn read_yaml_file(path: &str) -> anyhow::Result<Value> {
-    trace!("Loading file: {}", path);
-    let file = File::open(path)?;
-    let yaml: Value = serde_yaml::from_reader(file)?;
-    Ok(yaml)
+/// The value file that reads values from stdin, e.g. `-v -`
+pub const STDIN_VALUE_FILE: &str = "-";
+
+// Stdin can only be read once, so it is kept for every time the values are loaded
+static STDIN_CONTENTS: once_cell::sync::OnceCell<String> = once_cell::sync::OnceCell::new();
+
+/// Reads every document of a YAML file, or of stdin if the path is `-`. Empty documents, e.g. after
+/// a trailing `---`, are skipped.
+pub fn read_yaml_documents(path: &str) -> anyhow::Result<Vec<Value>> {
+    let contents = if path == STDIN_VALUE_FILE {
+        STDIN_CONTENTS
+            .get_or_try_init(|| -> anyhow::Result<String> {
+                trace!("Loading values from stdin");
+                let mut contents = String::new();
+                io::stdin().read_to_string(&mut contents)?;
+                Ok(contents)
+            })?
+            .clone()
+    } else {
+        trace!("Loading file: {}", path);
+        fs::read_to_string(path)?
+    };
+    parse_yaml_documents(&contents)
+}
+
+fn parse_yaml_documents(contents: &str) -> anyhow::Result<Vec<Value>> {
+    let mut documents = Vec::new();
+    // An empty file has no documents, rather than a single document missing its value
+    if contents.trim().is_empty() {
+        return Ok(documents);
+    }
+    for document in serde_yaml::Deserializer::from_str(contents) {
+        let yaml = Value::deserialize(document)?;
+        if !yaml.is_null() {
+            documents.push(yaml);
+        }
+    }
+    Ok(documents)
 }
 
 #[cfg(test)]
@@ -159,7 +801,12 @@ mod tests {
         );
 
         // Merge maps
-        merge_maps(&mut existing_map, new_map);
+        merge_maps(
+            &mut existing_map,
+            new_map,
+            &ListMergeStrategies::default(),
+            "",
+        );
 
         // Check merged map
         assert_eq!(
@@ -184,7 +831,7 @@ mod tests {
             values_path.to_str().unwrap(),
             override_path.to_str().unwrap(),
         ];
-        let output = load_yaml_files(&files)?;
+        let output = load_yaml_files(&files, &ListMergeStrategy::default())?;
         // Deserialize the expected YAML contents into a struct
         let expected_yaml: ExpectedFullValues = from_str(
             r#"---
@@ -218,7 +865,7 @@ mod tests {
             override_path.to_str().unwrap(),
             override_complex_path.to_str().unwrap(),
         ];
-        let output = load_yaml_files(&files)?;
+        let output = load_yaml_files(&files, &ListMergeStrategy::default())?;
         // Deserialize the expected YAML contents into a struct
         let expected_yaml: ExpectedFullValues = from_str(
             r#"---
@@ -248,13 +895,17 @@ mod tests {
         let override_complex_path =
             RelativePath::new("resources/test/test_values/override_complex.yaml")
                 .to_logical_path(&current_dir);
-        let files = vec![
+        let files = [
             values_path.to_str().unwrap(),
             override_path.to_str().unwrap(),
             override_complex_path.to_str().unwrap(),
-            "foo.bar=manual",
         ];
-        let output = load_yaml_files(&files)?;
+        let options = ValueOptions {
+            value_files: files.iter().map(|file| file.to_string()).collect(),
+            set: vec!["foo.bar=manual".to_string()],
+            ..Default::default()
+        };
+        let output = options.load()?;
         // Deserialize the expected YAML contents into a struct
         let expected_yaml: ExpectedFullValues = from_str(
             r#"---
@@ -284,15 +935,21 @@ mod tests {
         let override_complex_path =
             RelativePath::new("resources/test/test_values/override_complex.yaml")
                 .to_logical_path(&current_dir);
-        let files = vec![
+        let files = [
             values_path.to_str().unwrap(),
             override_path.to_str().unwrap(),
             override_complex_path.to_str().unwrap(),
-            "foo.bar=manual",
-            "world=world",
-            "foo.nested.map=wow",
         ];
-        let output = load_yaml_files(&files)?;
+        let options = ValueOptions {
+            value_files: files.iter().map(|file| file.to_string()).collect(),
+            set: vec![
+                "foo.bar=manual".to_string(),
+                "world=world".to_string(),
+                "foo.nested.map=wow".to_string(),
+            ],
+            ..Default::default()
+        };
+        let output = options.load()?;
         // Deserialize the expected YAML contents into a struct
         let expected_yaml: ExpectedFullValues = from_str(
             r#"---
@@ -326,7 +983,7 @@ mod tests {
             .to_logical_path(&current_dir);
 
         // Read the YAML file into a `serde_yaml::Value` object
-        let loaded_yaml = read_yaml_file(yaml_path.to_str().unwrap())?;
+        let loaded_yaml = read_yaml_documents(yaml_path.to_str().unwrap())?;
 
         // Deserialize the expected YAML contents into a struct
         let expected_yaml: ExpectedYamlOverride = from_str(
@@ -338,15 +995,15 @@ mod tests {
         let expected_value = serde_yaml::to_value(expected_yaml)?;
 
         // Test that the loaded YAML contents match the expected YAML contents
-        assert_eq!(expected_value, loaded_yaml);
+        assert_eq!(vec![expected_value], loaded_yaml);
 
         Ok(())
     }
 
     #[test]
     fn test_read_invalid_yaml_file() -> anyhow::Result<()> {
-        // Test that `read_yaml_file()` returns an error when given an invalid path
-        assert_matches!(read_yaml_file("invalid/path.yaml"), Err(_));
+        // Test that `read_yaml_documents()` returns an error when given an invalid path
+        assert_matches!(read_yaml_documents("invalid/path.yaml"), Err(_));
         Ok(())
     }
 
@@ -370,7 +1027,7 @@ mod tests {
         let yaml2: Value = from_str(yaml2_str)?;
 
         if let (Value::Mapping(ref mut map1), Value::Mapping(map2)) = (&mut yaml1, &yaml2) {
-            merge_maps(map1, map2.clone());
+            merge_maps(map1, map2.clone(), &ListMergeStrategies::default(), "");
         }
 
         // Now, let's define the expected merged YAML result
@@ -398,14 +1055,18 @@ mod tests {
             .to_logical_path(&current_dir);
         let override_path = RelativePath::new("resources/test/merge_lists/second.yaml")
             .to_logical_path(&current_dir);
-        let files = vec![
+        let files = [
             values_path.to_str().unwrap(),
             override_path.to_str().unwrap(),
-            "fruit.color=red",
         ];
+        let options = ValueOptions {
+            value_files: files.iter().map(|file| file.to_string()).collect(),
+            set: vec!["fruit.color=red".to_string()],
+            ..Default::default()
+        };
 
-        // Load and merge YAML contents from strings using `load_yaml_files` function
-        let merged_yaml = load_yaml_files(&files)?;
+        // Load and merge YAML contents from the files and strings
+        let merged_yaml = options.load()?;
 
         // Now, let's define the expected merged YAML result
         let expected_str = r#"
@@ -425,4 +1086,382 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_load_yaml_with_key_path_indices() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let values_path = RelativePath::new("resources/test/merge_lists/first.yaml")
+            .to_logical_path(&current_dir);
+        let options = ValueOptions {
+            value_files: vec![values_path.to_string_lossy().to_string()],
+            set: vec![
+                "items[1]=kiwi".to_string(),
+                "items[3]=plum".to_string(),
+                r"labels.com\.example\.team=web".to_string(),
+            ],
+            ..Default::default()
+        };
+        let merged_yaml = options.load()?;
+        let expected_str = r#"
+        items:
+          - apple
+          - kiwi
+          - null
+          - plum
+        world: "hello"
+        labels:
+          com.example.team: web
+    "#;
+        let expected: Value = from_str(expected_str)?;
+        assert_eq!(expected, merged_yaml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_default_values() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let directory = RelativePath::new("resources/test/defaults").to_logical_path(&current_dir);
+        let default_values = directory.join("values.yaml").to_string_lossy().to_string();
+        let override_values = RelativePath::new("resources/test/test_values/override.yaml")
+            .to_logical_path(&current_dir)
+            .to_string_lossy()
+            .to_string();
+
+        // The default values are the lowest precedence layer
+        let value_files = with_default_values(&directory, std::slice::from_ref(&override_values));
+        assert_eq!(value_files, vec![default_values.clone(), override_values]);
+        let merged_yaml = load_yaml_files(
+            &get_value_files_as_refs(&value_files),
+            &ListMergeStrategy::default(),
+        )?;
+        assert_eq!(merged_yaml["greeting"], Value::String("hello".to_owned()));
+        assert_eq!(merged_yaml["world"], Value::String("notString".to_owned()));
+
+        // The default values aren't loaded twice if the user passes them
+        let value_files = with_default_values(&directory, std::slice::from_ref(&default_values));
+        assert_eq!(value_files, vec![default_values]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_default_values_none_in_directory() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let directory = RelativePath::new("resources/test/simple").to_logical_path(&current_dir);
+        assert!(with_default_values(&directory, &[]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_options_load() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let values_path = RelativePath::new("resources/test/test_values/values.yaml")
+            .to_logical_path(&current_dir);
+        let options = ValueOptions {
+            value_files: vec![values_path.to_string_lossy().to_string()],
+            set: vec!["replicas=3".to_string(), "hello=false".to_string()],
+            set_string: vec!["version=1.10".to_string(), "replicas=4".to_string()],
+            ..Default::default()
+        };
+        let values = options.load()?;
+        // --set values keep their type and override the value files
+        assert_eq!(values["hello"], Value::Bool(false));
+        assert_eq!(values["world"], Value::String("string".to_owned()));
+        // --set-string values are always strings and take precedence over --set
+        assert_eq!(values["version"], Value::String("1.10".to_owned()));
+        assert_eq!(values["replicas"], Value::String("4".to_owned()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_options_set_json_and_file() -> anyhow::Result<()> {
+        let options = ValueOptions {
+            set: vec!["app.ports[0].target=8080".to_string()],
+            set_json: vec![r#"app={"ports": [{"target": 80}], "name": "web"}"#.to_string()],
+            set_file: vec!["app.config=resources/test/test_values/override.yaml".to_string()],
+            ..Default::default()
+        };
+        let values = options.load()?;
+        // --set-json is applied first, so --set can override part of it
+        assert_eq!(values["app"]["ports"][0]["target"], Value::from(8080));
+        assert_eq!(values["app"]["name"], Value::String("web".to_owned()));
+        assert_eq!(
+            values["app"]["config"],
+            Value::String(std::fs::read_to_string(
+                "resources/test/test_values/override.yaml"
+            )?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_layers_are_read_once() -> anyhow::Result<()> {
+        let directory = tempfile::tempdir()?;
+        let defaults_path = directory.path().join(DEFAULT_VALUES_FILE);
+        let values_path = directory.path().join("override.yaml");
+        let cert_path = directory.path().join("cert.pem");
+        std::fs::write(&defaults_path, "greeting: hello\nworld: default\n")?;
+        std::fs::write(
+            &values_path,
+            "composer:\n  merge:\n    ports: replace\nworld: override\nports: [80]\n",
+        )?;
+        std::fs::write(&cert_path, "certificate")?;
+        let options = ValueOptions {
+            value_files: vec![values_path.to_string_lossy().to_string()],
+            set_file: vec![format!("cert={}", cert_path.to_string_lossy())],
+            ..Default::default()
+        };
+        let layers = options.read_with_default_values(directory.path(), None)?;
+        // Every set of values is built from what was read, even once the files are gone
+        std::fs::remove_file(&defaults_path)?;
+        std::fs::remove_file(&values_path)?;
+        std::fs::remove_file(&cert_path)?;
+
+        let expected: Value =
+            from_str("greeting: hello\nworld: override\nports: [80]\ncert: certificate\n")?;
+        assert_eq!(layers.values()?, expected);
+        assert_eq!(layers.values_without_environment()?, expected);
+        let user_values: Value = from_str(
+            "world: override\nports: [80]\ncomposer:\n  merge:\n    ports: replace\ncert: certificate\n",
+        )?;
+        assert_eq!(layers.user_values()?, user_values);
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_file_with_equals_hint() {
+        let err =
+            load_yaml_files(&vec!["world=override"], &ListMergeStrategy::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Values YAML file world=override does not exist, use --set world=override to set a single value."
+        );
+    }
+
+    #[test]
+    fn test_stored_value_options_migrate_key_value_strings() -> anyhow::Result<()> {
+        // Older versions stored key-value strings amongst the value files
+        let options: ValueOptions = serde_json::from_str(
+            r#"{"value_files": ["resources/test/test_values/values.yaml", "world=override"], "set": ["hello=false"]}"#,
+        )?;
+        assert_eq!(
+            options,
+            ValueOptions {
+                value_files: vec!["resources/test/test_values/values.yaml".to_string()],
+                set: vec!["world=override".to_string(), "hello=false".to_string()],
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_merge_strategy_from_str() -> anyhow::Result<()> {
+        assert_eq!(
+            "append".parse::<ListMergeStrategy>()?,
+            ListMergeStrategy::Append
+        );
+        assert_eq!(
+            "replace".parse::<ListMergeStrategy>()?,
+            ListMergeStrategy::Replace
+        );
+        assert_eq!(
+            "merge-by-key".parse::<ListMergeStrategy>()?,
+            ListMergeStrategy::MergeByKey("name".to_string())
+        );
+        let strategy: ListMergeStrategy = "merge-by-key:id".parse()?;
+        assert_eq!(strategy, ListMergeStrategy::MergeByKey("id".to_string()));
+        assert_eq!(strategy.to_string(), "merge-by-key:id");
+        assert!("merge-by-key:".parse::<ListMergeStrategy>().is_err());
+        assert!("prepend".parse::<ListMergeStrategy>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_yaml_files_replace_lists() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let values_path = RelativePath::new("resources/test/merge_lists/first.yaml")
+            .to_logical_path(&current_dir);
+        let override_path = RelativePath::new("resources/test/merge_lists/second.yaml")
+            .to_logical_path(&current_dir);
+        let files = vec![
+            values_path.to_str().unwrap(),
+            override_path.to_str().unwrap(),
+        ];
+        let merged_yaml = load_yaml_files(&files, &ListMergeStrategy::Replace)?;
+        let expected: Value = from_str(
+            r#"
+        items:
+          - orange
+          - cherry
+        world: "goodbye"
+    "#,
+        )?;
+        assert_eq!(expected, merged_yaml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_yaml_files_merge_annotations() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let values_path = RelativePath::new("resources/test/merge_lists/annotated.yaml")
+            .to_logical_path(&current_dir);
+        let override_path = RelativePath::new("resources/test/merge_lists/annotated_override.yaml")
+            .to_logical_path(&current_dir);
+        let files = vec![
+            values_path.to_str().unwrap(),
+            override_path.to_str().unwrap(),
+        ];
+        // The annotations in the first file apply to the lists of every later file
+        let merged_yaml = load_yaml_files(&files, &ListMergeStrategy::default())?;
+        let expected: Value = from_str(
+            r#"
+        ports:
+          - 8080
+        services:
+          - name: web
+            image: caddy
+          - name: db
+            image: postgres
+          - name: cache
+            image: redis
+    "#,
+        )?;
+        assert_eq!(expected, merged_yaml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_lists_by_key_path() {
+        let mut strategies = ListMergeStrategies::default();
+        strategies.by_key_path.insert(
+            r"labels.com\.example".to_string(),
+            ListMergeStrategy::Replace,
+        );
+        let mut existing: Mapping =
+            from_str("labels:\n  com.example: [a]\n  other: [a]\n").unwrap();
+        let new: Mapping = from_str("labels:\n  com.example: [b]\n  other: [b]\n").unwrap();
+        merge_maps(&mut existing, new, &strategies, "");
+        let expected: Mapping = from_str("labels:\n  com.example: [b]\n  other: [a, b]\n").unwrap();
+        assert_eq!(expected, existing);
+    }
+
+    #[test]
+    fn test_invalid_merge_annotation() {
+        let mut values: Mapping = from_str("composer:\n  merge:\n    ports: prepend\n").unwrap();
+        let err =
+            take_merge_annotations(&mut values, &mut ListMergeStrategies::default()).unwrap_err();
+        assert_eq!(format!("{:#}", err), "Invalid list merge strategy for ports: Unknown list merge strategy 'prepend', must be one of append, replace, merge-by-key or merge-by-key:<field>");
+    }
+
+    #[test]
+    fn test_load_environment_values() -> anyhow::Result<()> {
+        let mut values: Mapping = from_str("database:\n  host: db\n  password: default\n")?;
+        let variables = vec![
+            ("COMPOSER_VALUE_database__password", "0123"),
+            ("COMPOSER_VALUE_log_level", "debug=true"),
+            ("PATH", "/usr/bin"),
+        ];
+        load_environment_values(
+            &mut values,
+            variables
+                .into_iter()
+                .map(|(name, value)| (OsString::from(name), OsString::from(value))),
+        )?;
+        let expected: Mapping =
+            from_str("database:\n  host: db\n  password: '0123'\nlog_level: debug=true\n")?;
+        assert_eq!(expected, values);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_environment_values_invalid_key_path() {
+        let variables = vec![(
+            OsString::from("COMPOSER_VALUE_database____password"),
+            OsString::from("secret"),
+        )];
+        let err = load_environment_values(&mut Mapping::new(), variables).unwrap_err();
+        // The value is never included in the error
+        assert!(!err.to_string().contains("secret"));
+        assert!(err
+            .to_string()
+            .starts_with("Environment variable COMPOSER_VALUE_database____password does not map"));
+    }
+
+    #[test]
+    fn test_load_multi_document_yaml() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let values_path = RelativePath::new("resources/test/test_values/multi_document.yaml")
+            .to_logical_path(&current_dir);
+        let files = vec![values_path.to_str().unwrap()];
+        let merged_yaml = load_yaml_files(&files, &ListMergeStrategy::default())?;
+        // Later documents override earlier ones, empty documents are skipped
+        let expected: Value = from_str(
+            r#"
+        hello: true
+        world: "second"
+        foo:
+          bar: "first"
+          baz: "second"
+    "#,
+        )?;
+        assert_eq!(expected, merged_yaml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_yaml_documents() -> anyhow::Result<()> {
+        assert!(parse_yaml_documents("")?.is_empty());
+        assert_eq!(
+            parse_yaml_documents("a: 1\n---\n---\nb: 2\n")?,
+            vec![from_str::<Value>("a: 1")?, from_str::<Value>("b: 2")?]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdin_read_once() {
+        let err = load_yaml_files(&vec!["-", "-"], &ListMergeStrategy::default()).unwrap_err();
+        assert_eq!(err.to_string(), "Values can only be read from stdin once.");
+    }
+
+    #[test]
+    fn test_to_stored_leaves_out_stdin() {
+        let options = ValueOptions {
+            value_files: vec!["values.yaml".to_string(), "-".to_string()],
+            set: vec!["a=b".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            options.to_stored(),
+            ValueOptions {
+                value_files: vec!["values.yaml".to_string()],
+                set: vec!["a=b".to_string()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_layered_with() {
+        let previous = ValueOptions {
+            value_files: vec!["snapshot.yaml".to_string()],
+            set: vec!["a=1".to_string()],
+            list_merge: ListMergeStrategy::Replace,
+            ..Default::default()
+        };
+        let overrides = ValueOptions {
+            value_files: vec!["override.yaml".to_string()],
+            set: vec!["a=2".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            previous.layered_with(&overrides),
+            ValueOptions {
+                value_files: vec!["snapshot.yaml".to_string(), "override.yaml".to_string()],
+                set: vec!["a=1".to_string(), "a=2".to_string()],
+                list_merge: ListMergeStrategy::Replace,
+                ..Default::default()
+            }
+        );
+    }
 }
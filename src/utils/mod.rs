@@ -1,8 +1,11 @@
 pub mod copy_file_utils;
 pub mod docker_compose;
 pub mod load_values;
+pub mod runtime;
 pub mod storage;
 pub mod template;
 pub mod test_utils;
+pub mod values_schema;
+pub mod wait;
 pub(crate) mod walk;
 mod yaml_string_parser;
@@ -0,0 +1,223 @@
+use crate::utils::docker_compose::silent_run;
+use anyhow::anyhow;
+use clap::ValueEnum;
+
+/// A container runtime able to run compose files, e.g. `docker compose` or `podman compose`
+pub trait ContainerRuntime: Sync {
+    /// The name of the runtime as given to `--runtime`
+    fn name(&self) -> &'static str;
+
+    /// The command and arguments running compose, e.g. `["docker", "compose"]`
+    fn compose_command(&self) -> &'static [&'static str];
+
+    /// The command managing containers directly, e.g. `docker` for `docker inspect`
+    fn container_command(&self) -> &'static str;
+
+    /// Whether `compose ps --format json` prints containers in the format docker compose does,
+    /// containers of runtimes that don't are listed by their compose project label instead
+    fn supports_compose_ps_json(&self) -> bool {
+        true
+    }
+
+    /// Whether the runtime is installed and its compose command works
+    fn is_installed(&self) -> bool {
+        let mut args = self.compose_command().to_vec();
+        args.push("version");
+        match silent_run(&args).status() {
+            Ok(status) => status.success(),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the full command line running a compose subcommand over the given compose files
+    /// as the given project, e.g. `docker compose -p <project> -f <file> up -d`.
+    fn compose_args<'a>(
+        &self,
+        project: &'a str,
+        files: &[&'a str],
+        args: &[&'a str],
+    ) -> Vec<&'a str> {
+        let mut command_line_args: Vec<&str> = self.compose_command().to_vec();
+        command_line_args.push("-p");
+        command_line_args.push(project);
+        for file in files {
+            command_line_args.push("-f");
+            command_line_args.push(file);
+        }
+        command_line_args.extend_from_slice(args);
+        command_line_args
+    }
+}
+
+/// Docker with the compose v2 plugin, `docker compose`
+pub struct DockerCompose;
+
+impl ContainerRuntime for DockerCompose {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    fn compose_command(&self) -> &'static [&'static str] {
+        &["docker", "compose"]
+    }
+
+    fn container_command(&self) -> &'static str {
+        "docker"
+    }
+}
+
+/// Podman with `podman compose`, which runs either podman-compose or a docker compose binary
+pub struct PodmanCompose;
+
+impl ContainerRuntime for PodmanCompose {
+    fn name(&self) -> &'static str {
+        "podman"
+    }
+
+    fn compose_command(&self) -> &'static [&'static str] {
+        &["podman", "compose"]
+    }
+
+    fn container_command(&self) -> &'static str {
+        "podman"
+    }
+
+    // podman-compose prints podman's own container format
+    fn supports_compose_ps_json(&self) -> bool {
+        false
+    }
+}
+
+/// The legacy standalone compose v1 binary, `docker-compose`
+pub struct LegacyDockerCompose;
+
+impl ContainerRuntime for LegacyDockerCompose {
+    fn name(&self) -> &'static str {
+        "docker-compose"
+    }
+
+    fn compose_command(&self) -> &'static [&'static str] {
+        &["docker-compose"]
+    }
+
+    fn container_command(&self) -> &'static str {
+        "docker"
+    }
+
+    // Compose v1 has no JSON output for ps
+    fn supports_compose_ps_json(&self) -> bool {
+        false
+    }
+}
+
+/// The runtimes in the order they are detected in
+const DETECTION_ORDER: [&dyn ContainerRuntime; 3] =
+    [&DockerCompose, &PodmanCompose, &LegacyDockerCompose];
+
+/// The container runtime chosen with `--runtime`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum RuntimeSelection {
+    /// Use the first installed of docker compose, podman compose and docker-compose
+    Auto,
+    Docker,
+    Podman,
+    DockerCompose,
+}
+
+impl RuntimeSelection {
+    /// Returns the runtime for this selection, `None` for auto
+    fn get_runtime(&self) -> Option<&'static dyn ContainerRuntime> {
+        match self {
+            RuntimeSelection::Auto => None,
+            RuntimeSelection::Docker => Some(&DockerCompose),
+            RuntimeSelection::Podman => Some(&PodmanCompose),
+            RuntimeSelection::DockerCompose => Some(&LegacyDockerCompose),
+        }
+    }
+
+    /// Returns the selected runtime if it is installed, for auto the first installed runtime is
+    /// detected.
+    pub fn resolve(&self) -> anyhow::Result<&'static dyn ContainerRuntime> {
+        match self.get_runtime() {
+            Some(runtime) if runtime.is_installed() => Ok(runtime),
+            Some(runtime) => Err(anyhow!(
+                "{} is not installed. Please install it or choose another --runtime.",
+                runtime.compose_command().join(" ")
+            )),
+            None => {
+                for runtime in DETECTION_ORDER {
+                    if runtime.is_installed() {
+                        debug!("Detected container runtime {}", runtime.name());
+                        return Ok(runtime);
+                    }
+                }
+                Err(anyhow!(
+                    "Could not find a container runtime. Please install docker compose, podman compose or docker-compose before using composer."
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_args() {
+        trace!("Running test_compose_args.");
+        assert_eq!(
+            DockerCompose.compose_args("composer-app", &["a.yaml", "b.yaml"], &["up", "-d"]),
+            vec![
+                "docker",
+                "compose",
+                "-p",
+                "composer-app",
+                "-f",
+                "a.yaml",
+                "-f",
+                "b.yaml",
+                "up",
+                "-d"
+            ]
+        );
+        assert_eq!(
+            LegacyDockerCompose.compose_args("composer-app", &["a.yaml"], &["down"]),
+            vec![
+                "docker-compose",
+                "-p",
+                "composer-app",
+                "-f",
+                "a.yaml",
+                "down"
+            ]
+        );
+        assert_eq!(
+            PodmanCompose.compose_args("composer-app", &["a.yaml"], &["ps"])[..2],
+            ["podman", "compose"]
+        );
+    }
+
+    #[test]
+    fn test_get_runtime() {
+        trace!("Running test_get_runtime.");
+        assert!(RuntimeSelection::Auto.get_runtime().is_none());
+        let names: Vec<&str> = [
+            RuntimeSelection::Docker,
+            RuntimeSelection::Podman,
+            RuntimeSelection::DockerCompose,
+        ]
+        .iter()
+        .filter_map(|selection| selection.get_runtime())
+        .map(|runtime| runtime.name())
+        .collect();
+        assert_eq!(names, vec!["docker", "podman", "docker-compose"]);
+        // The names of the runtimes match the values of --runtime
+        for selection in RuntimeSelection::value_variants() {
+            if let Some(runtime) = selection.get_runtime() {
+                let value = selection.to_possible_value().unwrap();
+                assert_eq!(value.get_name(), runtime.name());
+            }
+        }
+    }
+}
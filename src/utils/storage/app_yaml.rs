@@ -8,6 +8,9 @@ use std::path::Path;
 pub struct AppYaml {
     pub(crate) name: String,
     pub(crate) version: String,
+    /// If true the templates are always rendered in strict mode, see `--strict`
+    #[serde(default)]
+    pub(crate) strict: bool,
 }
 
 pub fn load_app_yaml<P: AsRef<Path>>(path: P) -> Result<AppYaml> {
@@ -48,6 +51,24 @@ unknown_field: "unknown"
         let data = result.unwrap();
         assert_eq!(data.name, "simple-test");
         assert_eq!(data.version, "1.0.0");
+        assert!(!data.strict);
+    }
+
+    #[test]
+    fn test_load_yaml_file_strict() {
+        let yaml_data = r#"
+name: "strict-test"
+version: "1.0.0"
+strict: true
+"#;
+
+        let mut temp_file = NamedTempFile::new().expect("Failed to create temporary file");
+        temp_file
+            .write_all(yaml_data.as_bytes())
+            .expect("Failed to write to temporary file");
+
+        let data = load_app_yaml(temp_file.path()).unwrap();
+        assert!(data.strict);
     }
 
     #[test]
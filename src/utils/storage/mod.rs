@@ -1,5 +1,6 @@
 pub mod app_yaml;
 pub mod models;
 pub mod read_from;
+pub mod revisions;
 pub mod update_storage;
 pub mod write_to_storage;
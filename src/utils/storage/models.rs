@@ -1,3 +1,4 @@
+use crate::utils::load_values::ValueOptions;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -8,15 +9,31 @@ pub struct PersistedApplication {
     pub state: ApplicationState,
     pub app_name: String,
     pub compose_path: String,
-    #[serde(default)]
-    pub value_files: Vec<String>,
+    #[serde(flatten)]
+    pub values: ValueOptions,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum ApplicationState {
     STARTING,
     RUNNING,
     ERROR,
+    STOPPED,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Revision {
+    pub revision: u32,
+    pub version: String,
+    pub timestamp: i64,
+    pub state: ApplicationState,
+    pub app_name: String,
+    pub compose_path: String,
+    #[serde(flatten)]
+    pub values: ValueOptions,
+    #[serde(default)]
+    pub description: String,
 }
 
 use std::fmt;
@@ -27,6 +44,7 @@ impl fmt::Display for ApplicationState {
             ApplicationState::STARTING => "STARTING",
             ApplicationState::RUNNING => "RUNNING",
             ApplicationState::ERROR => "ERROR",
+            ApplicationState::STOPPED => "STOPPED",
         };
         write!(f, "{:<15}", state_str)
     }
@@ -55,6 +55,7 @@ pub fn if_application_exists(id: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use crate::utils::load_values::ValueOptions;
     use crate::utils::storage::models::{ApplicationState, PersistedApplication};
     use crate::utils::storage::read_from::{get_all_from_storage, get_application_by_id};
     use crate::utils::storage::write_to_storage::append_to_storage;
@@ -77,7 +78,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec!["abc".to_string()],
+            values: ValueOptions::from_value_files(vec!["abc".to_string()]),
         };
         let id2 = "test_get_all_from_storage_sunny_day_2";
         let app2 = PersistedApplication {
@@ -87,7 +88,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec![],
+            values: ValueOptions::default(),
         };
         // Append both apps to storage
         append_to_storage(&app)?;
@@ -134,7 +135,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec!["abc".to_string(), "def".to_string()],
+            values: ValueOptions::from_value_files(vec!["abc".to_string(), "def".to_string()]),
         };
         let id2 = "not_looked_for";
         let app2 = PersistedApplication {
@@ -144,7 +145,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec![],
+            values: ValueOptions::default(),
         };
         // Append both apps to storage
         append_to_storage(&app)?;
@@ -172,7 +173,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec![],
+            values: ValueOptions::default(),
         };
         // Append both apps to storage
         append_to_storage(&app)?;
@@ -0,0 +1,307 @@
+use crate::utils::copy_file_utils::{copy_files_with_ignorefile, get_composer_directory};
+use crate::utils::storage::models::{ApplicationState, PersistedApplication, Revision};
+use anyhow::anyhow;
+use anyhow::Context;
+use serde_yaml::Value;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+const REVISION_FILE: &str = "revision.json";
+const REVISION_FILES_DIRECTORY: &str = "files";
+const VALUES_SNAPSHOT_FILE: &str = "values.yaml";
+const USER_VALUES_SNAPSHOT_FILE: &str = "user-values.yaml";
+
+/// The values a revision was deployed with, stored alongside it so they can be reused and
+/// inspected even if the value files have since changed. Environment values are never included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValuesSnapshot {
+    /// The consolidated values the templates were rendered with
+    pub values: Value,
+    /// Only the values given by the user, without the template's default values
+    pub user_values: Value,
+}
+
+/// Returns the directory holding every recorded revision of an application, i.e.
+/// `~/.composer/.history/<id>`.
+pub fn get_history_directory(id: &str) -> anyhow::Result<PathBuf> {
+    Ok(get_composer_directory()?.join(".history").join(id))
+}
+
+/// Returns the values snapshot of a revision, or `None` if it was recorded before values were
+/// snapshotted.
+pub fn get_revision_values(id: &str, revision: u32) -> anyhow::Result<Option<ValuesSnapshot>> {
+    let revision_directory = get_history_directory(id)?.join(revision.to_string());
+    let values_file = revision_directory.join(VALUES_SNAPSHOT_FILE);
+    let user_values_file = revision_directory.join(USER_VALUES_SNAPSHOT_FILE);
+    if !values_file.is_file() || !user_values_file.is_file() {
+        return Ok(None);
+    }
+    let read_snapshot = |path: &Path| -> anyhow::Result<Value> {
+        let reader = BufReader::new(File::open(path)?);
+        serde_yaml::from_reader(reader)
+            .with_context(|| format!("Could not parse YAML in '{:?}'", path))
+    };
+    Ok(Some(ValuesSnapshot {
+        values: read_snapshot(&values_file)?,
+        user_values: read_snapshot(&user_values_file)?,
+    }))
+}
+
+/// Returns the directory holding the rendered files of a single revision.
+pub fn get_revision_files_directory(id: &str, revision: u32) -> anyhow::Result<PathBuf> {
+    Ok(get_history_directory(id)?
+        .join(revision.to_string())
+        .join(REVISION_FILES_DIRECTORY))
+}
+
+/// Records a new revision for the given application.
+///
+/// The rendered files in `rendered_directory` are copied into the history directory alongside
+/// a `revision.json` describing the application at the time of recording, and the values snapshot
+/// if there is one. Revisions are numbered sequentially starting from 1.
+///
+/// Revisions are recorded from the staged files before they are swapped into `~/.composer/<id>`,
+/// so runtime data written into the application directory never ends up in the history. Once the
+/// application is up its state and description are updated with `update_revision`.
+///
+/// # Arguments
+///
+/// * `application` - The application as it is about to be persisted to config.json.
+/// * `rendered_directory` - The directory containing the staged rendered files.
+/// * `values` - The values the application is deployed with.
+/// * `description` - A short human readable description, e.g. "Install complete".
+///
+/// # Returns
+///
+/// The `Revision` that has been recorded.
+pub fn record_revision(
+    application: &PersistedApplication,
+    rendered_directory: &Path,
+    values: Option<&ValuesSnapshot>,
+    description: &str,
+) -> anyhow::Result<Revision> {
+    let next_revision = get_revisions(&application.id)?
+        .last()
+        .map(|revision| revision.revision + 1)
+        .unwrap_or(1);
+    let revision = Revision {
+        revision: next_revision,
+        version: application.version.clone(),
+        timestamp: application.timestamp,
+        state: application.state.clone(),
+        app_name: application.app_name.clone(),
+        compose_path: application.compose_path.clone(),
+        values: application.values.clone(),
+        description: description.to_string(),
+    };
+
+    let files_directory = get_revision_files_directory(&application.id, next_revision)?;
+    fs::create_dir_all(&files_directory)
+        .with_context(|| format!("Could not create directory '{:?}'", &files_directory))?;
+    copy_files_with_ignorefile(rendered_directory, &files_directory, None)?;
+
+    let revision_directory =
+        get_history_directory(&application.id)?.join(next_revision.to_string());
+    if let Some(values) = values {
+        for (file_name, snapshot) in [
+            (VALUES_SNAPSHOT_FILE, &values.values),
+            (USER_VALUES_SNAPSHOT_FILE, &values.user_values),
+        ] {
+            let snapshot_file = revision_directory.join(file_name);
+            fs::write(&snapshot_file, serde_yaml::to_string(snapshot)?)
+                .with_context(|| format!("Could not write values to '{:?}'", &snapshot_file))?;
+        }
+    }
+
+    write_revision(&application.id, &revision)?;
+    trace!(
+        "Recorded revision {} for application {}",
+        next_revision,
+        application.id
+    );
+    Ok(revision)
+}
+
+/// Returns every recorded revision of an application, ordered from oldest to newest.
+/// An application without any history returns an empty list.
+pub fn get_revisions(id: &str) -> anyhow::Result<Vec<Revision>> {
+    let history_directory = get_history_directory(id)?;
+    if !history_directory.exists() {
+        return Ok(Vec::new());
+    }
+    let mut revisions = Vec::new();
+    for entry in fs::read_dir(&history_directory)? {
+        let revision_file = entry?.path().join(REVISION_FILE);
+        if !revision_file.is_file() {
+            continue;
+        }
+        let reader = BufReader::new(File::open(&revision_file)?);
+        let revision: Revision = serde_json::from_reader(reader)
+            .with_context(|| format!("Could not parse JSON in '{:?}'", &revision_file))?;
+        revisions.push(revision);
+    }
+    revisions.sort_by_key(|revision| revision.revision);
+    Ok(revisions)
+}
+
+pub fn get_revision(id: &str, revision: u32) -> anyhow::Result<Revision> {
+    get_revisions(id)?
+        .into_iter()
+        .find(|recorded| recorded.revision == revision)
+        .ok_or_else(|| anyhow!("Revision {} of application {} not found", revision, id))
+}
+
+/// Updates the state and description of a recorded revision once the application it was recorded
+/// for has been brought up, or has failed to.
+pub fn update_revision(
+    id: &str,
+    revision: u32,
+    state: ApplicationState,
+    description: &str,
+) -> anyhow::Result<Revision> {
+    let mut recorded = get_revision(id, revision)?;
+    recorded.state = state;
+    recorded.description = description.to_string();
+    write_revision(id, &recorded)?;
+    Ok(recorded)
+}
+
+/// Removes a single recorded revision, used when the application it was recorded for never came
+/// up and the previous files have been restored.
+pub fn delete_revision(id: &str, revision: u32) -> anyhow::Result<()> {
+    let revision_directory = get_history_directory(id)?.join(revision.to_string());
+    if revision_directory.exists() {
+        fs::remove_dir_all(revision_directory)?;
+    }
+    Ok(())
+}
+
+fn write_revision(id: &str, revision: &Revision) -> anyhow::Result<()> {
+    let revision_file = get_history_directory(id)?
+        .join(revision.revision.to_string())
+        .join(REVISION_FILE);
+    let writer = BufWriter::new(
+        File::create(&revision_file)
+            .with_context(|| format!("Could not create file '{:?}'", &revision_file))?,
+    );
+    serde_json::to_writer(writer, revision)
+        .with_context(|| format!("Could not write revision to '{:?}'", &revision_file))
+}
+
+pub fn delete_revisions(id: &str) -> anyhow::Result<()> {
+    let history_directory = get_history_directory(id)?;
+    if history_directory.exists() {
+        fs::remove_dir_all(history_directory)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::load_values::ValueOptions;
+    use crate::utils::storage::models::{ApplicationState, PersistedApplication};
+    use crate::utils::storage::revisions::{
+        delete_revision, delete_revisions, get_revision, get_revision_files_directory,
+        get_revision_values, get_revisions, record_revision, update_revision, ValuesSnapshot,
+    };
+    use serial_test::serial;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn test_application(id: &str, version: &str) -> PersistedApplication {
+        PersistedApplication {
+            id: id.to_string(),
+            version: version.to_string(),
+            timestamp: 0,
+            state: ApplicationState::RUNNING,
+            app_name: id.to_string(),
+            compose_path: id.to_string(),
+            values: ValueOptions::from_value_files(vec!["values.yaml".to_string()]),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_record_revisions_are_numbered_sequentially() -> anyhow::Result<()> {
+        let id = "test_record_revisions_are_numbered_sequentially";
+        let rendered = tempdir()?;
+        fs::write(rendered.path().join("docker-compose.jinja2"), "services: {}")?;
+
+        let values = ValuesSnapshot {
+            values: serde_yaml::from_str("greeting: hello\nworld: override\n")?,
+            user_values: serde_yaml::from_str("world: override\n")?,
+        };
+        let first = record_revision(
+            &test_application(id, "1.0.0"),
+            rendered.path(),
+            None,
+            "Install",
+        )?;
+        let second = record_revision(
+            &test_application(id, "1.1.0"),
+            rendered.path(),
+            Some(&values),
+            "Upgrade",
+        )?;
+        let revisions = get_revisions(id)?;
+        let files_copied = get_revision_files_directory(id, 2)?
+            .join("docker-compose.jinja2")
+            .exists();
+        let first_values = get_revision_values(id, 1)?;
+        let second_values = get_revision_values(id, 2)?;
+        // Clean up before assertions in case they fail
+        delete_revisions(id)?;
+
+        assert_eq!(first.revision, 1);
+        assert_eq!(second.revision, 2);
+        assert_eq!(revisions, vec![first, second]);
+        assert!(files_copied);
+        assert_eq!(first_values, None);
+        assert_eq!(second_values, Some(values));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_update_and_delete_revision() -> anyhow::Result<()> {
+        trace!("Running test_update_and_delete_revision.");
+        let id = "test_update_and_delete_revision";
+        let rendered = tempdir()?;
+        fs::write(rendered.path().join("docker-compose.jinja2"), "services: {}")?;
+
+        let mut application = test_application(id, "1.0.0");
+        application.state = ApplicationState::STARTING;
+        record_revision(&application, rendered.path(), None, "Install started")?;
+        record_revision(&application, rendered.path(), None, "Upgrade started")?;
+        // Files written after recording, e.g. by the running application, are not recorded
+        fs::write(rendered.path().join("data.db"), "runtime data")?;
+        let updated = update_revision(id, 1, ApplicationState::RUNNING, "Install complete")?;
+        delete_revision(id, 2)?;
+        let revisions = get_revisions(id)?;
+        let runtime_data_recorded = get_revision_files_directory(id, 1)?
+            .join("data.db")
+            .exists();
+        // Clean up before assertions in case they fail
+        delete_revisions(id)?;
+
+        assert_eq!(updated.state, ApplicationState::RUNNING);
+        assert_eq!(updated.description, "Install complete");
+        assert_eq!(revisions, vec![updated]);
+        assert!(!runtime_data_recorded);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_revision_not_found() -> anyhow::Result<()> {
+        let id = "test_get_revision_not_found";
+        let err = get_revision(id, 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Revision 3 of application test_get_revision_not_found not found"
+        );
+        assert!(get_revisions(id)?.is_empty());
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@ use anyhow::anyhow;
 use anyhow::Context;
 
 use crate::utils::storage::read_from::get_all_from_storage;
+use crate::utils::storage::revisions::delete_revisions;
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read};
 use std::path::PathBuf;
@@ -81,6 +82,7 @@ pub fn delete_application_by_id(id: &str) -> anyhow::Result<()> {
         if app_directory.exists() {
             fs::remove_dir_all(app_directory)?;
         }
+        delete_revisions(id)?;
         Ok(())
     } else {
         Err(anyhow!(
@@ -92,6 +94,7 @@ pub fn delete_application_by_id(id: &str) -> anyhow::Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use crate::utils::load_values::ValueOptions;
     use crate::utils::storage::models::{ApplicationState, PersistedApplication};
     use crate::utils::storage::read_from::{get_application_by_id, if_application_exists};
     use crate::utils::storage::write_to_storage::{append_to_storage, delete_application_by_id};
@@ -112,7 +115,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec!["123".to_string(), "abc".to_string()],
+            values: ValueOptions::from_value_files(vec!["123".to_string(), "abc".to_string()]),
         };
         // Append the app to storage
         append_to_storage(&app)?;
@@ -142,7 +145,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec![],
+            values: ValueOptions::default(),
         };
         // Backup config.json
         let (composer_json_config, composer_json_config_backup) = backup_composer_config()?;
@@ -172,7 +175,7 @@ mod tests {
             state: ApplicationState::STARTING,
             app_name: id.to_string(),
             compose_path: id.to_string(),
-            value_files: vec![],
+            values: ValueOptions::default(),
         };
         // Append the app to storage
         append_to_storage(&app)?;
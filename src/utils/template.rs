@@ -1,5 +1,6 @@
-use minijinja::Environment;
-use serde_yaml::Value;
+use minijinja::value::ValueKind;
+use minijinja::{escape_formatter, AutoEscape, Environment, ErrorKind, UndefinedBehavior};
+use serde_yaml::{Mapping, Value};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -10,45 +11,61 @@ use std::path::Path;
 ///
 /// * `path` - The file path to the Jinja2 template.
 /// * `values_yaml` - A `serde_yaml::Value` containing the variables to inject into the template.
+/// * `strict` - If true any use of an undefined variable is an error rather than rendering empty.
 ///
 /// # Returns
 ///
 /// * `Ok(String)` containing the rendered template if successful.
 /// * `Err(anyhow::Error)` if an error occurs during rendering.
-pub fn render_template(path: &str, values_yaml: Value) -> anyhow::Result<String> {
+pub fn render_template(path: &str, values_yaml: Value, strict: bool) -> anyhow::Result<String> {
+    render_template_with_cwd(path, get_template_directory(path), values_yaml, strict)
+}
+
+/// Renders a Jinja2 template like [`render_template`], but with `composer.cwd` set to `cwd`
+/// rather than the directory of the template. Used when a template is rendered somewhere other
+/// than where it will be used, e.g. a staging directory.
+///
+/// # Arguments
+///
+/// * `path` - The file path to the Jinja2 template.
+/// * `cwd` - The directory `composer.cwd` is rendered as.
+/// * `values_yaml` - A `serde_yaml::Value` containing the variables to inject into the template.
+/// * `strict` - If true any use of an undefined variable is an error rather than rendering empty.
+pub fn render_template_with_cwd(
+    path: &str,
+    cwd: &Path,
+    values_yaml: Value,
+    strict: bool,
+) -> anyhow::Result<String> {
     // Load the template file into a string
-    let mut template_file = File::open(path)?;
-    let mut template_string = String::new();
-    template_file.read_to_string(&mut template_string)?;
+    let template_string = read_template(path)?;
 
     // Create a Jinja environment
-    let mut env = Environment::new();
-
-    // Get the directory of the template file
-    let template_dir = Path::new(path)
-        .parent()
-        .unwrap_or_else(|| Path::new("."))
-        .to_path_buf();
-
-    // Retrieve global variables to add
-    let global_vars = get_global_variables(&template_dir)?;
-
-    // Remove 'composer' key from the input YAML values
-    let cleansed_values = remove_composer_key(values_yaml)?;
+    let mut env = create_environment();
+    if strict {
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+    }
 
-    // Merge the cleansed YAML values with the global variables
-    let merged_values = merge_yaml(cleansed_values, global_vars)?;
+    // Merge the values with the composer global variables
+    let merged_values = get_template_values(cwd, values_yaml)?;
 
-    // Add the template to the environment
-    let template_key = "template";
-    env.add_template(template_key, &template_string)?;
-    let template = env.get_template(template_key)?;
+    // Add the template to the environment, named by its path so errors point at the file
+    env.add_template(path, &template_string)?;
+    let template = env.get_template(path)?;
 
     // Convert the merged data to minijinja values
-    let ctx = minijinja::value::Value::from_serializable(&merged_values);
+    let ctx = minijinja::value::Value::from_serialize(&merged_values);
 
     // Render the template with the input data
     let rendered = template.render(&ctx).map_err(|e| {
+        if e.kind() == ErrorKind::UndefinedError {
+            return anyhow::anyhow!(
+                "Failed to render template {}: undefined variable '{}' at line {}",
+                path,
+                get_error_expression(&template_string, &e).unwrap_or("unknown"),
+                e.line().unwrap_or_default()
+            );
+        }
         anyhow::anyhow!(
             "Failed to render template {}: due to an error in the template. Error: {}",
             path,
@@ -60,6 +77,202 @@ pub fn render_template(path: &str, values_yaml: Value) -> anyhow::Result<String>
     Ok(rendered)
 }
 
+/// Finds the variables a Jinja2 template references that are not defined in the provided values.
+///
+/// Nested attribute access is followed, so `{{ foo.bar }}` is reported as `foo.bar` when `foo`
+/// exists but has no `bar` key. Variables declared in the template itself (`set`, loops, macros)
+/// and builtin globals such as `range` are not reported. Neither are variables the template
+/// handles being undefined, e.g. with the `default` filter, an `is defined` test, `if` or `or`:
+/// every variable missing from the values is only reported if rendering fails without it while
+/// every other missing variable is given a placeholder.
+///
+/// # Arguments
+///
+/// * `path` - The file path to the Jinja2 template.
+/// * `values_yaml` - A `serde_yaml::Value` containing the variables to inject into the template.
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` containing the sorted undefined variables, empty if all are defined.
+/// * `Err(anyhow::Error)` if the template could not be read or parsed.
+pub fn find_undefined_variables(path: &str, values_yaml: Value) -> anyhow::Result<Vec<String>> {
+    let template_string = read_template(path)?;
+    let mut env = create_environment();
+    // Undefined variables fail to render unless they are only checked, e.g. `{% if foo %}`
+    env.set_undefined_behavior(UndefinedBehavior::SemiStrict);
+    let merged_values = get_template_values(get_template_directory(path), values_yaml)?;
+    env.add_template("template", &template_string)?;
+    let template = env.get_template("template")?;
+    let globals: Vec<&str> = env.globals().map(|(name, _)| name).collect();
+
+    let mut missing: Vec<String> = template
+        .undeclared_variables(true)
+        .into_iter()
+        .filter(|variable| {
+            let root = variable.split('.').next().unwrap_or(variable);
+            !globals.contains(&root) && !is_defined(&merged_values, variable)
+        })
+        .collect();
+    missing.sort();
+
+    let mut undefined = Vec::new();
+    for variable in &missing {
+        let mut values = merged_values.clone();
+        // Variables on the same path, e.g. foo for foo.bar, are left undefined along with it
+        let others = missing
+            .iter()
+            .filter(|other| !is_same_path(other, variable));
+        for other in others {
+            define_placeholder(&mut values, other);
+        }
+        let ctx = minijinja::value::Value::from_serialize(&values);
+        if let Err(e) = template.render(&ctx) {
+            if e.kind() == ErrorKind::UndefinedError {
+                undefined.push(variable.clone());
+            }
+        }
+    }
+    Ok(undefined)
+}
+
+// Creates the Jinja environment every template is rendered with
+fn create_environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    // Templates produce compose and config files, never HTML, so nothing is escaped
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    env.set_formatter(|out, state, value| match value.kind() {
+        ValueKind::Bool | ValueKind::None | ValueKind::Seq | ValueKind::Map => {
+            out.write_str(&format_value(value, false))?;
+            Ok(())
+        }
+        _ => escape_formatter(out, state, value),
+    });
+    env.add_filter("join", join);
+    env
+}
+
+// The join filter, formatting the items like the formatter so booleans and none are joined the
+// way minijinja 0.30 joined them
+fn join(value: minijinja::Value, separator: Option<String>) -> Result<String, minijinja::Error> {
+    let separator = separator.unwrap_or_default();
+    if let Some(string) = value.as_str() {
+        let characters: Vec<String> = string.chars().map(String::from).collect();
+        return Ok(characters.join(&separator));
+    }
+    if value.is_undefined() || value.is_none() {
+        return Ok(String::new());
+    }
+    let items: Vec<String> = value
+        .try_iter()?
+        .map(|item| format_value(&item, false))
+        .collect();
+    Ok(items.join(&separator))
+}
+
+// Formats a value the way minijinja 0.30 did, which existing templates were written against.
+// minijinja 2 prints booleans as True/False and none as None, including within lists and maps.
+fn format_value(value: &minijinja::Value, nested: bool) -> String {
+    match value.kind() {
+        ValueKind::Bool => value.is_true().to_string(),
+        ValueKind::None if !nested => "none".to_string(),
+        ValueKind::Seq | ValueKind::Map => match value.try_iter() {
+            Ok(items) if value.kind() == ValueKind::Seq => {
+                let items: Vec<String> = items.map(|item| format_value(&item, true)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Ok(keys) => {
+                let entries: Vec<String> = keys
+                    .map(|key| {
+                        let item = value.get_item(&key).unwrap_or_default();
+                        format!("{:?}: {}", key, format_value(&item, true))
+                    })
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Err(_) => value.to_string(),
+        },
+        _ if nested => format!("{:?}", value),
+        _ => value.to_string(),
+    }
+}
+
+// Returns the source of the expression a render error was raised for, e.g. `imgae.tag`
+fn get_error_expression<'a>(template: &'a str, error: &minijinja::Error) -> Option<&'a str> {
+    error
+        .range()
+        .and_then(|range| template.get(range))
+        .map(|expression| expression.trim())
+}
+
+fn read_template(path: &str) -> anyhow::Result<String> {
+    let mut template_file = File::open(path)?;
+    let mut template_string = String::new();
+    template_file.read_to_string(&mut template_string)?;
+    Ok(template_string)
+}
+
+// Returns the directory of the template file
+fn get_template_directory(path: &str) -> &Path {
+    Path::new(path).parent().unwrap_or_else(|| Path::new("."))
+}
+
+// Returns the values a template is rendered with, the user values plus the composer globals
+fn get_template_values(cwd: &Path, values_yaml: Value) -> anyhow::Result<Value> {
+    // Retrieve global variables to add
+    let global_vars = get_global_variables(cwd)?;
+
+    // Remove 'composer' key from the input YAML values
+    let cleansed_values = remove_composer_key(values_yaml)?;
+
+    // Merge the cleansed YAML values with the global variables
+    merge_yaml(cleansed_values, global_vars)
+}
+
+// Checks whether a dotted variable path, e.g. foo.bar, resolves within the values. Paths that
+// continue past a non mapping value can't be checked so are assumed to be defined.
+fn is_defined(values: &Value, variable: &str) -> bool {
+    let mut current = values;
+    for key in variable.split('.') {
+        match current {
+            Value::Mapping(mapping) => match mapping.get(key) {
+                Some(next) => current = next,
+                None => return false,
+            },
+            _ => return true,
+        }
+    }
+    true
+}
+
+// Checks whether one dotted variable path is the other or leads to it, e.g. foo and foo.bar
+fn is_same_path(first: &str, second: &str) -> bool {
+    let (shorter, longer) = if first.len() <= second.len() {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    longer == shorter
+        || longer
+            .strip_prefix(shorter)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+// Defines a dotted variable path as an empty string, replacing anything in its way with mappings,
+// so a template renders as if the variable was given
+fn define_placeholder(values: &mut Value, variable: &str) {
+    let mut current = values;
+    for key in variable.split('.') {
+        if !current.is_mapping() {
+            *current = Value::Mapping(Mapping::new());
+        }
+        current = current
+            .as_mapping_mut()
+            .unwrap()
+            .entry(Value::String(key.to_string()))
+            .or_insert(Value::String(String::new()));
+    }
+}
+
 /// Retrieves global variables to be injected into the template.
 ///
 /// # Arguments
@@ -146,7 +359,7 @@ fn merge_yaml(base: Value, override_val: Value) -> anyhow::Result<Value> {
 
 #[cfg(test)]
 mod tests {
-    use super::render_template;
+    use super::{find_undefined_variables, render_template};
     use relative_path::RelativePath;
     use serde_yaml::Value;
     use std::env::current_dir;
@@ -162,7 +375,7 @@ mod tests {
         val: world
         ";
         let value_file: Value = serde_yaml::from_str(yaml)?;
-        let output_string = render_template(template.to_str().unwrap(), value_file)?;
+        let output_string = render_template(template.to_str().unwrap(), value_file, false)?;
         let expected_string = "Hello, world!".to_string();
         assert_eq!(expected_string, output_string);
         Ok(())
@@ -181,7 +394,7 @@ mod tests {
                 bool_val: true
         ";
         let value_file: Value = serde_yaml::from_str(yaml)?;
-        let output_string = render_template(template.to_str().unwrap(), value_file)?;
+        let output_string = render_template(template.to_str().unwrap(), value_file, false)?;
         let expected_string = "test default_str true".to_string();
         assert_eq!(expected_string, output_string);
         Ok(())
@@ -200,7 +413,7 @@ mod tests {
         ";
         let value_file: Value = serde_yaml::from_str(yaml)?;
         assert_matches!(
-            render_template(template.to_str().unwrap(), value_file),
+            render_template(template.to_str().unwrap(), value_file, false),
             Err(_)
         );
         Ok(())
@@ -247,6 +460,7 @@ mod tests {
                 anyhow::anyhow!("Failed to convert template path to string")
             })?,
             value_file,
+            false,
         )?;
 
         // Determine the expected `composer.cwd` value (the directory containing the template)
@@ -298,6 +512,7 @@ mod tests {
                 anyhow::anyhow!("Failed to convert template path to string")
             })?,
             value_file,
+            false,
         )?;
 
         // Determine the expected 'composer.cwd' value (the directory containing the template)
@@ -328,5 +543,115 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_find_undefined_variables() -> anyhow::Result<()> {
+        trace!("Running test_find_undefined_variables.");
+        let current_dir = current_dir()?;
+        let template = RelativePath::new("resources/test/templates/nested-default.jinja2")
+            .to_logical_path(&current_dir);
+        let yaml = "
+        nested:
+            some: 'other_value'
+        ";
+        let value_file: Value = serde_yaml::from_str(yaml)?;
+        // default is guarded with `or` in the template so isn't reported
+        let undefined = find_undefined_variables(template.to_str().unwrap(), value_file)?;
+        assert_eq!(undefined, vec!["nested.second_level.bool_val".to_string()]);
+        Ok(())
+    }
 
+    #[test]
+    fn test_find_undefined_variables_guards() -> anyhow::Result<()> {
+        trace!("Running test_find_undefined_variables_guards.");
+        let current_dir = current_dir()?;
+        let template = RelativePath::new("resources/test/lint_guards/guards.jinja2")
+            .to_logical_path(&current_dir);
+        let value_file: Value = serde_yaml::from_str("defined: value")?;
+        // Comments and string literals aren't variables, and guards are only trusted as far as
+        // they keep the template rendering, so the right hand side of an `or` is still reported
+        let undefined = find_undefined_variables(template.to_str().unwrap(), value_file)?;
+        assert_eq!(
+            undefined,
+            vec!["unguarded".to_string(), "unguarded_second".to_string()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_undefined_variables_all_defined() -> anyhow::Result<()> {
+        trace!("Running test_find_undefined_variables_all_defined.");
+        let current_dir = current_dir()?;
+        let template = RelativePath::new("resources/test/templates/cwd_template.jinja2")
+            .to_logical_path(&current_dir);
+        let value_file: Value = serde_yaml::from_str("val: world")?;
+        // composer.cwd is a global so is always defined
+        let undefined = find_undefined_variables(template.to_str().unwrap(), value_file)?;
+        assert!(undefined.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_strict_undefined_variable() -> anyhow::Result<()> {
+        trace!("Running test_render_strict_undefined_variable.");
+        let current_dir = current_dir()?;
+        let template = RelativePath::new("resources/test/strict/strict.jinja2")
+            .to_logical_path(&current_dir);
+        let template_str = template.to_str().unwrap();
+        let value_file: Value = serde_yaml::from_str("image:\n  name: nginx\n  tag: latest")?;
+        // Lenient rendering silently renders the typo as empty
+        let lenient = render_template(template_str, value_file.clone(), false)?;
+        assert!(lenient.contains("image: \"nginx:\""));
+        let err = render_template(template_str, value_file, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Failed to render template {}: undefined variable 'image.tga' at line 3",
+                template_str
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_matches_previous_minijinja_output() -> anyhow::Result<()> {
+        trace!("Running test_render_matches_previous_minijinja_output.");
+        let current_dir = current_dir()?;
+        let directory = RelativePath::new("resources/test/compat").to_logical_path(&current_dir);
+        let template = directory.join("compat.jinja2");
+        let value_file: Value =
+            serde_yaml::from_str(&std::fs::read_to_string(directory.join("values.yaml"))?)?;
+        // expected.yaml is the output of minijinja 0.30, which templates were written against
+        let expected = std::fs::read_to_string(directory.join("expected.yaml"))?;
+        let rendered = render_template(template.to_str().unwrap(), value_file, false)?;
+        assert_eq!(rendered, expected);
+        // Booleans stay YAML booleans rather than becoming True/False
+        let compose: Value = serde_yaml::from_str(&rendered)?;
+        let environment = &compose["services"]["web"]["environment"];
+        assert_eq!(environment["DEBUG"], Value::Bool(true));
+        assert_eq!(environment["VERBOSE"], Value::Bool(false));
+        assert_eq!(environment["FLAGS"].as_str(), Some("[true, false, None]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_filters_match_previous_minijinja_output() -> anyhow::Result<()> {
+        trace!("Running test_render_filters_match_previous_minijinja_output.");
+        let current_dir = current_dir()?;
+        let directory =
+            RelativePath::new("resources/test/compat_filters").to_logical_path(&current_dir);
+        let template = directory.join("compat.jinja2");
+        let value_file: Value =
+            serde_yaml::from_str(&std::fs::read_to_string(directory.join("values.yaml"))?)?;
+        // expected.yaml is the output of minijinja 0.30 for the filters, tests, loops, macros and
+        // operators it shipped with
+        let expected = std::fs::read_to_string(directory.join("expected.yaml"))?;
+        let rendered = render_template(template.to_str().unwrap(), value_file.clone(), false)?;
+        assert_eq!(rendered, expected);
+        // The ~ operator builds its string before the formatter sees it, so unlike 0.30 it
+        // concatenates booleans and none as True, False and None
+        let concat = directory.join("concat.jinja2");
+        let rendered = render_template(concat.to_str().unwrap(), value_file, false)?;
+        assert_eq!(rendered, "debug=True none=None");
+        Ok(())
+    }
 }
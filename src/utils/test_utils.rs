@@ -5,6 +5,7 @@ use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 use crate::utils::storage::read_from::if_application_exists;
+use crate::utils::storage::revisions::delete_revisions;
 use crate::utils::storage::write_to_storage::delete_application_by_id;
 
 #[allow(dead_code)]
@@ -59,5 +60,7 @@ pub fn clean_up_test_folder(id: &str) -> anyhow::Result<()> {
         // This might fail but we tried
         let _ = delete_application_by_id(id);
     }
+    // Remove any recorded revisions left behind
+    delete_revisions(id)?;
     Ok(())
 }
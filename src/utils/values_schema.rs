@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Context};
+use jsonschema::paths::{JSONPointer, PathChunk};
+use jsonschema::JSONSchema;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// The schema files looked for next to app.yaml, in order of preference
+const SCHEMA_FILES: [&str; 2] = ["values.schema.json", "values.schema.yaml"];
+
+/// Returns the values schema shipped in a template directory, if there is one.
+pub fn get_values_schema_path(directory: &Path) -> Option<PathBuf> {
+    SCHEMA_FILES
+        .iter()
+        .map(|file_name| directory.join(file_name))
+        .find(|path| path.is_file())
+}
+
+fn load_schema(path: &Path) -> anyhow::Result<serde_json::Value> {
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    if path.extension() == Some("json".as_ref()) {
+        serde_json::from_reader(file)
+            .with_context(|| format!("Could not parse JSON in {}", path.display()))
+    } else {
+        let schema: serde_yaml::Value = serde_yaml::from_reader(file)
+            .with_context(|| format!("Could not parse YAML in {}", path.display()))?;
+        Ok(serde_json::to_value(schema)?)
+    }
+}
+
+// Converts a JSON pointer, e.g. /services/0/port, into the YAML path services[0].port
+fn to_yaml_path(pointer: &JSONPointer) -> String {
+    let mut path = String::new();
+    for chunk in pointer.iter() {
+        match chunk {
+            PathChunk::Index(index) => path.push_str(&format!("[{}]", index)),
+            PathChunk::Property(property) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(property);
+            }
+            PathChunk::Keyword(keyword) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(keyword);
+            }
+        }
+    }
+    if path.is_empty() {
+        "<root>".to_string()
+    } else {
+        path
+    }
+}
+
+/// Validates the consolidated values against the template directory's `values.schema.json` or
+/// `values.schema.yaml`. Directories without a schema always pass.
+///
+/// # Arguments
+///
+/// * `directory` - The template directory containing app.yaml.
+/// * `values` - The consolidated values the templates will be rendered with.
+///
+/// # Returns
+///
+/// * `Ok(())` if there is no schema or the values match it.
+/// * `Err(anyhow::Error)` listing every violation with its YAML path otherwise.
+pub fn validate_values(directory: &Path, values: &serde_yaml::Value) -> anyhow::Result<()> {
+    let schema_path = match get_values_schema_path(directory) {
+        Some(schema_path) => schema_path,
+        None => return Ok(()),
+    };
+    trace!("Validating values against {}", schema_path.display());
+    let schema = load_schema(&schema_path)?;
+    let compiled = JSONSchema::compile(&schema)
+        .map_err(|e| anyhow!("Invalid values schema {}: {}", schema_path.display(), e))?;
+    let instance = serde_json::to_value(values)
+        .context("Could not convert the values to JSON to validate them")?;
+    let violations: Vec<String> = match compiled.validate(&instance) {
+        Ok(()) => return Ok(()),
+        Err(errors) => errors
+            .map(|error| format!("  {}: {}", to_yaml_path(&error.instance_path), error))
+            .collect(),
+    };
+    Err(anyhow!(
+        "Values do not match the schema {}, found {} violation(s):\n{}",
+        schema_path.display(),
+        violations.len(),
+        violations.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use relative_path::RelativePath;
+    use std::env::current_dir;
+
+    #[test]
+    fn test_validate_values_without_schema() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let directory = RelativePath::new("resources/test/simple/").to_logical_path(&current_dir);
+        let values: serde_yaml::Value = serde_yaml::from_str("anything: 1")?;
+        assert!(validate_values(&directory, &values).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_values_against_schema() -> anyhow::Result<()> {
+        let current_dir = current_dir()?;
+        let directory = RelativePath::new("resources/test/schema/").to_logical_path(&current_dir);
+        let values: serde_yaml::Value =
+            serde_yaml::from_str("image: nginx\nports:\n  - target: http\n")?;
+        let err = validate_values(&directory, &values).unwrap_err();
+        let schema_path = directory.join("values.schema.json");
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Values do not match the schema {}, found 2 violation(s):\n  ports[0].target: \"http\" is not of type \"integer\"\n  <root>: \"world\" is a required property",
+                schema_path.display()
+            )
+        );
+
+        let values: serde_yaml::Value =
+            serde_yaml::from_str("image: nginx\nworld: hi\nports:\n  - target: 80\n")?;
+        assert!(validate_values(&directory, &values).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_yaml_path() {
+        let pointer = JSONPointer::from(&["services", "web"][..]);
+        assert_eq!(to_yaml_path(&pointer), "services.web");
+        assert_eq!(to_yaml_path(&JSONPointer::default()), "<root>");
+    }
+}
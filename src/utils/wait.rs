@@ -0,0 +1,263 @@
+use crate::utils::copy_file_utils::get_composer_directory;
+use crate::utils::docker_compose::{
+    compose_ps, compose_service_logs, get_compose_files_by_id, get_compose_service_names,
+    ContainerStatus,
+};
+use anyhow::anyhow;
+use clap::Args;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// How often the containers of an application are polled while waiting for it
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// The number of consecutive polls every service has to be ready for, so containers that crash
+/// shortly after starting aren't reported as ready
+const REQUIRED_READY_POLLS: u32 = 2;
+/// The number of log lines printed for every service that did not become ready
+const FAILED_SERVICE_LOG_LINES: usize = 10;
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct WaitOptions {
+    /// Wait until every service is running, and healthy if it has a health check, before marking
+    ///   the application as RUNNING, otherwise it is marked as ERROR
+    #[clap(long)]
+    pub wait: bool,
+    /// Used with --wait, the number of seconds to wait for the services to become ready
+    #[clap(long, requires = "wait", default_value_t = 300)]
+    pub timeout: u64,
+}
+
+/// Whether the containers of a service are ready
+#[derive(Debug, Clone, PartialEq)]
+enum ServiceReadiness {
+    Ready,
+    /// The service may still become ready, e.g. it is starting or restarting
+    NotReady(String),
+    /// The service will never become ready without intervention
+    Failed(String),
+}
+
+/// The readiness of a single service of an application
+#[derive(Debug, Clone, PartialEq)]
+struct ServiceReport {
+    compose_file: String,
+    service: String,
+    readiness: ServiceReadiness,
+}
+
+// Containers that exited successfully are treated as ready so one-off services such as
+// migrations don't block the wait
+fn get_container_readiness(container: &ContainerStatus) -> ServiceReadiness {
+    match (container.state.as_str(), container.health.as_str()) {
+        ("running", "" | "healthy") => ServiceReadiness::Ready,
+        ("running", health) => ServiceReadiness::NotReady(health.to_string()),
+        ("exited", _) if container.exit_code == 0 => ServiceReadiness::Ready,
+        ("exited", _) => {
+            ServiceReadiness::Failed(format!("exited with code {}", container.exit_code))
+        }
+        ("dead", _) => ServiceReadiness::Failed("dead".to_string()),
+        (state, _) => ServiceReadiness::NotReady(state.to_string()),
+    }
+}
+
+/// Returns the readiness of a service from all of its containers, a service is only ready once
+/// every one of its containers is.
+fn get_service_readiness(service: &str, containers: &[ContainerStatus]) -> ServiceReadiness {
+    let readiness: Vec<ServiceReadiness> = containers
+        .iter()
+        .filter(|container| container.service == service)
+        .map(get_container_readiness)
+        .collect();
+    if readiness.is_empty() {
+        return ServiceReadiness::NotReady("not created".to_string());
+    }
+    if let Some(failed) = readiness
+        .iter()
+        .find(|readiness| matches!(readiness, ServiceReadiness::Failed(_)))
+    {
+        return failed.clone();
+    }
+    readiness
+        .into_iter()
+        .find(|readiness| *readiness != ServiceReadiness::Ready)
+        .unwrap_or(ServiceReadiness::Ready)
+}
+
+// Returns the readiness of every service defined in the compose files of an application
+fn get_service_reports(id: &str) -> anyhow::Result<Vec<ServiceReport>> {
+    let composer_id_directory = get_composer_directory()?.join(id);
+    let mut reports = Vec::new();
+    for compose_file in get_compose_files_by_id(id)? {
+        let containers = compose_ps(&compose_file)?;
+        let relative_compose_file = Path::new(&compose_file)
+            .strip_prefix(&composer_id_directory)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| compose_file.clone());
+        for service in get_compose_service_names(&compose_file) {
+            reports.push(ServiceReport {
+                readiness: get_service_readiness(&service, &containers),
+                compose_file: relative_compose_file.clone(),
+                service,
+            });
+        }
+    }
+    Ok(reports)
+}
+
+// Describes every service that is not ready, followed by its last log lines
+fn format_failure_report(id: &str, reports: &[ServiceReport]) -> String {
+    let composer_id_directory = get_composer_directory().ok().map(|path| path.join(id));
+    let mut lines = Vec::new();
+    for report in reports {
+        let reason = match &report.readiness {
+            ServiceReadiness::Ready => continue,
+            ServiceReadiness::NotReady(reason) | ServiceReadiness::Failed(reason) => reason,
+        };
+        lines.push(format!(
+            "  {} ({}): {}",
+            report.service, report.compose_file, reason
+        ));
+        let logs = composer_id_directory
+            .as_ref()
+            .map(|directory| directory.join(&report.compose_file))
+            .and_then(|compose_path| {
+                compose_service_logs(
+                    &compose_path.to_string_lossy(),
+                    &report.service,
+                    FAILED_SERVICE_LOG_LINES,
+                )
+                .ok()
+            })
+            .unwrap_or_default();
+        for log_line in logs.lines().filter(|line| !line.trim().is_empty()) {
+            lines.push(format!("    | {}", log_line));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Polls the containers of an application until every service defined in its compose files is
+/// running, and healthy if it has a health check, or until the timeout is reached.
+///
+/// # Returns
+///
+/// An error listing every service that did not become ready with its last log lines, returned as
+/// soon as a container exits with a non-zero code or once the timeout is reached.
+pub fn wait_for_application(id: &str, timeout: Duration) -> anyhow::Result<()> {
+    info!(
+        "Waiting up to {}s for the services of application {} to become ready",
+        timeout.as_secs(),
+        id
+    );
+    let deadline = Instant::now() + timeout;
+    let mut ready_polls = 0;
+    loop {
+        let reports = get_service_reports(id)?;
+        let failed = reports
+            .iter()
+            .any(|report| matches!(report.readiness, ServiceReadiness::Failed(_)));
+        if failed {
+            return Err(anyhow!(
+                "Services of application {} have failed:\n{}",
+                id,
+                format_failure_report(id, &reports)
+            ));
+        }
+        if reports
+            .iter()
+            .all(|report| report.readiness == ServiceReadiness::Ready)
+        {
+            ready_polls += 1;
+            if ready_polls >= REQUIRED_READY_POLLS {
+                return Ok(());
+            }
+        } else {
+            ready_polls = 0;
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "Services of application {} did not become ready within {}s:\n{}",
+                id,
+                timeout.as_secs(),
+                format_failure_report(id, &reports)
+            ));
+        }
+        trace!("Application {} is not ready yet: {:?}", id, reports);
+        sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(service: &str, state: &str, health: &str, exit_code: i32) -> ContainerStatus {
+        ContainerStatus {
+            name: format!("app-{}-1", service),
+            service: service.to_string(),
+            image: String::new(),
+            state: state.to_string(),
+            health: health.to_string(),
+            exit_code,
+            publishers: None,
+        }
+    }
+
+    #[test]
+    fn test_get_container_readiness() {
+        trace!("Running test_get_container_readiness.");
+        assert_eq!(
+            get_container_readiness(&container("web", "running", "", 0)),
+            ServiceReadiness::Ready
+        );
+        assert_eq!(
+            get_container_readiness(&container("web", "running", "healthy", 0)),
+            ServiceReadiness::Ready
+        );
+        assert_eq!(
+            get_container_readiness(&container("web", "running", "starting", 0)),
+            ServiceReadiness::NotReady("starting".to_string())
+        );
+        assert_eq!(
+            get_container_readiness(&container("web", "restarting", "", 1)),
+            ServiceReadiness::NotReady("restarting".to_string())
+        );
+        assert_eq!(
+            get_container_readiness(&container("migrate", "exited", "", 0)),
+            ServiceReadiness::Ready
+        );
+        assert_eq!(
+            get_container_readiness(&container("web", "exited", "", 137)),
+            ServiceReadiness::Failed("exited with code 137".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_service_readiness() {
+        trace!("Running test_get_service_readiness.");
+        let containers = vec![
+            container("web", "running", "healthy", 0),
+            container("web", "running", "unhealthy", 0),
+            container("db", "running", "", 0),
+            container("worker", "exited", "", 1),
+            container("worker", "running", "", 0),
+        ];
+        assert_eq!(
+            get_service_readiness("web", &containers),
+            ServiceReadiness::NotReady("unhealthy".to_string())
+        );
+        assert_eq!(
+            get_service_readiness("db", &containers),
+            ServiceReadiness::Ready
+        );
+        assert_eq!(
+            get_service_readiness("worker", &containers),
+            ServiceReadiness::Failed("exited with code 1".to_string())
+        );
+        assert_eq!(
+            get_service_readiness("cache", &containers),
+            ServiceReadiness::NotReady("not created".to_string())
+        );
+    }
+}
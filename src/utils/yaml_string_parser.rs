@@ -1,29 +1,50 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use serde_yaml::{Mapping, Value};
-use std::borrow::Cow;
+use std::fs;
 
-/// Parses a string in the format "x.y.z=foo" into its appropriate YAML representation.
+/// The largest list index accepted in a key path, so a typo can't allocate a huge list
+const MAX_INDEX: usize = 65535;
+
+/// A single step of a key path, either a mapping key or a list index
+#[derive(Debug, PartialEq)]
+enum KeySegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Sets the value of a string in the format "x.y.z=foo" in existing values.
 ///
 /// This function takes a string in the format of a dot-separated key path, followed by an equals sign and a value.
-/// It then creates a YAML mapping with nested mappings for each key in the path, with the final key-value pair being
-/// added as a string value to the nested mapping.
+/// It walks the existing values along the key path, creating nested mappings for any key that doesn't exist yet,
+/// and sets the value at the end of the path. Only the addressed value is replaced, anything in the way that isn't
+/// a mapping or list as the key path expects is replaced. The value is parsed as a YAML scalar, so `replicas=3` is
+/// the integer 3 and `debug=true` is a boolean, anything that isn't an integer, float, boolean or null is kept as a
+/// string. Use `set_yaml_string_as_string` to always insert a string.
+///
+/// The key path can index into lists with `[n]`, e.g. `services[0].image=nginx` replaces the image of the first
+/// service of an existing list, any missing list items before the index are filled with null. A dot that is part
+/// of a key is escaped with a backslash, e.g. `labels.com\.example\.team=web`.
 ///
 /// # Errors
 ///
 /// This function returns an `anyhow::Error` if the input string is not in the expected format of "x.y.z=foo" or if
-/// the key path is empty.
+/// the key path is empty or invalid.
 ///
 /// # Examples
 ///
 /// ```
-/// use serde_yaml::Value;
+/// use serde_yaml::{Mapping, Value};
 /// use anyhow::Result;
 ///
 /// fn main() -> Result<()> {
-///     let yaml_str = "abc.bcd.dge=xyz";
-///     let yaml_value = parse_yaml_string(yaml_str)?;
+///     let mut values = Mapping::new();
+///     set_yaml_string(&mut values, "abc.bcd.dge=xyz")?;
+///     set_yaml_string(&mut values, "replicas=3")?;
+///     set_yaml_string(&mut values, "ports[1]=80")?;
 ///
-///     assert_eq!(yaml_value["abc"]["bcd"]["dge"], Value::String("xyz".to_owned()));
+///     assert_eq!(values["abc"]["bcd"]["dge"], Value::String("xyz".to_owned()));
+///     assert_eq!(values["replicas"], Value::from(3));
+///     assert_eq!(values["ports"][1], Value::from(80));
 ///
 ///     Ok(())
 /// }
@@ -31,18 +52,69 @@ use std::borrow::Cow;
 ///
 /// # Arguments
 ///
+/// * `values` - The values to set the value in.
 /// * `yaml_str` - A string in the format of "x.y.z=foo", where the key path is separated by dots and
 ///                followed by an equals sign and a value.
-///
-/// # Returns
-///
-/// A `serde_yaml::Value` object representing the YAML mapping created from the input string.
-///
-/// # Panics
-///
-/// This function may panic if there is an internal error when accessing nested mappings.
-pub(crate) fn parse_yaml_string(yaml_str: &str) -> anyhow::Result<Value> {
-    let (key_path, value) = yaml_str.split_once("=").ok_or_else(|| {
+pub(crate) fn set_yaml_string(values: &mut Mapping, yaml_str: &str) -> anyhow::Result<()> {
+    set_at_key_path(values, yaml_str, |value| Ok(parse_scalar(value)))
+}
+
+/// Sets the value of a string in the format "x.y.z=foo" in existing values like `set_yaml_string`,
+/// but the value is always inserted as a string, e.g. `version=1.10` stays "1.10" rather than
+/// becoming the float 1.1.
+pub(crate) fn set_yaml_string_as_string(
+    values: &mut Mapping,
+    yaml_str: &str,
+) -> anyhow::Result<()> {
+    set_at_key_path(values, yaml_str, |value| {
+        Ok(Value::String(value.to_owned()))
+    })
+}
+
+/// Reads the file of a string in the format "x.y.z=path", returning "x.y.z=contents" to set with
+/// `set_yaml_string_as_string`, e.g. a certificate. The file is read once up front, so a file that
+/// can only be read once such as a pipe can be set in more than one set of values.
+pub(crate) fn read_yaml_string_file(yaml_str: &str) -> anyhow::Result<String> {
+    match yaml_str.split_once('=') {
+        Some((key_path, path)) if !key_path.is_empty() => {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read file {}", path))?;
+            Ok(format!("{}={}", key_path, contents))
+        }
+        // Invalid strings are reported when they are set
+        _ => Ok(yaml_str.to_string()),
+    }
+}
+
+/// Sets the value of a string in the format "x.y.z=json" in existing values like `set_yaml_string`,
+/// but the value is parsed as JSON, so whole lists and mappings can be set,
+/// e.g. `ports=[{"target": 80}]`.
+pub(crate) fn set_yaml_string_as_json(values: &mut Mapping, yaml_str: &str) -> anyhow::Result<()> {
+    set_at_key_path(values, yaml_str, |json| {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .with_context(|| format!("Failed to parse JSON value {}", json))?;
+        Ok(serde_yaml::to_value(value)?)
+    })
+}
+
+// Parses a value as a YAML scalar, falling back to a string for anything else including
+// sequences and mappings, and an empty value which YAML would otherwise treat as null
+fn parse_scalar(value: &str) -> Value {
+    if value.is_empty() {
+        return Value::String(String::new());
+    }
+    match serde_yaml::from_str::<Value>(value) {
+        Ok(scalar @ (Value::Null | Value::Bool(_) | Value::Number(_))) => scalar,
+        _ => Value::String(value.to_owned()),
+    }
+}
+
+fn set_at_key_path(
+    values: &mut Mapping,
+    yaml_str: &str,
+    parse_value: fn(&str) -> anyhow::Result<Value>,
+) -> anyhow::Result<()> {
+    let (key_path, value) = yaml_str.split_once('=').ok_or_else(|| {
         anyhow!(
             "Failed to split YAML string: {}, must be the format x.y.z=foo",
             yaml_str
@@ -56,32 +128,132 @@ pub(crate) fn parse_yaml_string(yaml_str: &str) -> anyhow::Result<Value> {
         ));
     }
 
-    let keys = key_path.split(".");
-    let mut map = Mapping::new();
-    let mut nested_map = &mut map;
-
-    for key in keys.clone().take(keys.clone().count().saturating_sub(1)) {
-        let new_map = Mapping::new();
-        nested_map.insert(Value::from(Cow::Borrowed(key)), Value::Mapping(new_map));
-        nested_map = match nested_map.get_mut(key).unwrap() {
-            Value::Mapping(map) => map,
-            _ => unreachable!(),
-        };
+    let segments = parse_key_path(key_path)
+        .map_err(|e| anyhow!("Invalid key path '{}' in {}: {}", key_path, yaml_str, e))?;
+    let value = parse_value(value)?;
+    // The key path always starts with a key, so the root stays a mapping
+    let mut root = Value::Mapping(std::mem::take(values));
+    insert_at_path(&mut root, &segments, value);
+    if let Value::Mapping(map) = root {
+        *values = map;
     }
+    Ok(())
+}
 
-    let last_key = keys.last().unwrap();
-    nested_map.insert(
-        Value::from(Cow::Borrowed(last_key)),
-        Value::String(value.to_owned()),
-    );
+// Splits a key path such as services[0].labels.com\.example into its keys and list indices
+fn parse_key_path(key_path: &str) -> anyhow::Result<Vec<KeySegment>> {
+    let mut segments = Vec::new();
+    let mut key = String::new();
+    // Set straight after a list index, which can only be followed by '.', '[' or the end
+    let mut after_index = false;
+    let mut chars = key_path.chars();
+    while let Some(c) = chars.next() {
+        if after_index && c != '.' && c != '[' {
+            return Err(anyhow!("a list index must be followed by '.' or '['"));
+        }
+        match c {
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| anyhow!("it ends with an unfinished escape"))?;
+                key.push(escaped);
+            }
+            '.' => {
+                if !after_index {
+                    if key.is_empty() {
+                        return Err(anyhow!("it contains an empty key"));
+                    }
+                    segments.push(KeySegment::Key(std::mem::take(&mut key)));
+                }
+                after_index = false;
+            }
+            '[' => {
+                if !key.is_empty() {
+                    segments.push(KeySegment::Key(std::mem::take(&mut key)));
+                } else if segments.is_empty() {
+                    return Err(anyhow!("it must start with a key"));
+                } else if !after_index {
+                    return Err(anyhow!("it contains an empty key"));
+                }
+                let mut index = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    index.push(c);
+                }
+                if !closed {
+                    return Err(anyhow!("'[{}' is missing a closing ']'", index));
+                }
+                let index: usize = index
+                    .parse()
+                    .map_err(|_| anyhow!("'[{}]' is not a valid list index", index))?;
+                if index > MAX_INDEX {
+                    return Err(anyhow!(
+                        "list index {} is larger than the maximum of {}",
+                        index,
+                        MAX_INDEX
+                    ));
+                }
+                segments.push(KeySegment::Index(index));
+                after_index = true;
+            }
+            _ => key.push(c),
+        }
+    }
+    if !key.is_empty() {
+        segments.push(KeySegment::Key(key));
+    } else if !after_index {
+        return Err(anyhow!("it ends with an empty key"));
+    }
+    Ok(segments)
+}
 
-    Ok(Value::Mapping(map))
+// Walks the key path from the given node, creating mappings and lists as they are needed
+fn insert_at_path(node: &mut Value, segments: &[KeySegment], value: Value) {
+    match segments.split_first() {
+        None => *node = value,
+        Some((KeySegment::Key(key), rest)) => {
+            if !node.is_mapping() {
+                *node = Value::Mapping(Mapping::new());
+            }
+            if let Value::Mapping(map) = node {
+                let child = map.entry(Value::String(key.clone())).or_insert(Value::Null);
+                insert_at_path(child, rest, value);
+            }
+        }
+        Some((KeySegment::Index(index), rest)) => {
+            if !node.is_sequence() {
+                *node = Value::Sequence(Vec::new());
+            }
+            if let Value::Sequence(list) = node {
+                if list.len() <= *index {
+                    list.resize(index + 1, Value::Null);
+                }
+                insert_at_path(&mut list[*index], rest, value);
+            }
+        }
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_yaml::from_str;
 
+    fn parse_yaml_string(yaml_str: &str) -> anyhow::Result<Value> {
+        let mut map = Mapping::new();
+        set_yaml_string(&mut map, yaml_str)?;
+        Ok(Value::Mapping(map))
+    }
+
+    fn parse_yaml_string_as_string(yaml_str: &str) -> anyhow::Result<Value> {
+        let mut map = Mapping::new();
+        set_yaml_string_as_string(&mut map, yaml_str)?;
+        Ok(Value::Mapping(map))
+    }
+
     #[test]
     fn test_parse_yaml_string() -> anyhow::Result<()> {
         let actual = parse_yaml_string("foo.bar=baz")?;
@@ -138,4 +310,162 @@ mod tests {
         assert_eq!(expected_err, actual_err);
         Ok(())
     }
+
+    #[test]
+    fn test_parse_yaml_string_typed() -> anyhow::Result<()> {
+        let actual = parse_yaml_string("app.replicas=3")?;
+        assert_eq!(actual["app"]["replicas"], Value::from(3));
+        assert_eq!(parse_yaml_string("debug=true")?["debug"], Value::Bool(true));
+        assert_eq!(parse_yaml_string("ratio=0.5")?["ratio"], Value::from(0.5));
+        assert_eq!(parse_yaml_string("image=null")?["image"], Value::Null);
+        assert_eq!(
+            parse_yaml_string("empty=")?["empty"],
+            Value::String(String::new())
+        );
+        // Only scalars are parsed, anything else stays a string
+        assert_eq!(
+            parse_yaml_string("list=[a, b]")?["list"],
+            Value::String("[a, b]".to_owned())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_yaml_string_as_string() -> anyhow::Result<()> {
+        let actual = parse_yaml_string_as_string("app.version=1.10")?;
+        let yaml = "
+        app:
+            version: '1.10'
+        ";
+        let expected: Value = from_str(yaml)?;
+        assert_eq!(expected, actual);
+        assert_eq!(
+            parse_yaml_string_as_string("debug=true")?["debug"],
+            Value::String("true".to_owned())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_yaml_string_list_index() -> anyhow::Result<()> {
+        let actual = parse_yaml_string("services[1].ports[0]=80")?;
+        let yaml = "
+        services:
+            - null
+            - ports:
+                - 80
+        ";
+        let expected: Value = from_str(yaml)?;
+        assert_eq!(expected, actual);
+        let actual = parse_yaml_string("matrix[0][1]=x")?;
+        assert_eq!(actual["matrix"][0][1], Value::String("x".to_owned()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_yaml_string_escaped_dot() -> anyhow::Result<()> {
+        let actual = parse_yaml_string(r"labels.com\.example\.team=web")?;
+        let yaml = "
+        labels:
+            com.example.team: web
+        ";
+        let expected: Value = from_str(yaml)?;
+        assert_eq!(expected, actual);
+        assert_eq!(
+            parse_yaml_string(r"back\\slash=1")?[r"back\slash"],
+            Value::from(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_yaml_string_in_existing_values() -> anyhow::Result<()> {
+        let mut values: Mapping = from_str(
+            "
+        services:
+            - image: nginx
+              ports: [80]
+            - image: redis
+        ",
+        )?;
+        set_yaml_string(&mut values, "services[0].image=caddy")?;
+        set_yaml_string(&mut values, "services[1].ports[0]=6379")?;
+        let expected: Mapping = from_str(
+            "
+        services:
+            - image: caddy
+              ports: [80]
+            - image: redis
+              ports: [6379]
+        ",
+        )?;
+        assert_eq!(expected, values);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_yaml_string_invalid_key_path() {
+        let cases = [
+            ("a..b=1", "it contains an empty key"),
+            ("a.=1", "it ends with an empty key"),
+            ("[0]=1", "it must start with a key"),
+            ("a.[0]=1", "it contains an empty key"),
+            ("a[0]b=1", "a list index must be followed by '.' or '['"),
+            ("a[x]=1", "'[x]' is not a valid list index"),
+            ("a[0=1", "'[0' is missing a closing ']'"),
+            (r"a\=1", "it ends with an unfinished escape"),
+            (
+                "a[70000]=1",
+                "list index 70000 is larger than the maximum of 65535",
+            ),
+        ];
+        for (yaml_str, reason) in cases {
+            let key_path = yaml_str.split_once('=').unwrap().0;
+            assert_eq!(
+                parse_yaml_string(yaml_str).unwrap_err().to_string(),
+                format!(
+                    "Invalid key path '{}' in {}: {}",
+                    key_path, yaml_str, reason
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_yaml_string_file() -> anyhow::Result<()> {
+        let mut values = Mapping::new();
+        let set_file =
+            read_yaml_string_file("config.override=resources/test/test_values/override.yaml")?;
+        set_yaml_string_as_string(&mut values, &set_file)?;
+        let contents = fs::read_to_string("resources/test/test_values/override.yaml")?;
+        assert_eq!(values["config"]["override"], Value::String(contents));
+
+        let err = read_yaml_string_file("cert=missing.pem").unwrap_err();
+        assert_eq!(err.to_string(), "Failed to read file missing.pem");
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_yaml_string_as_json() -> anyhow::Result<()> {
+        let mut values = Mapping::new();
+        set_yaml_string_as_json(
+            &mut values,
+            r#"app.ports=[{"target": 80}, {"target": 443}]"#,
+        )?;
+        set_yaml_string_as_json(&mut values, r#"app.name="1.10""#)?;
+        let expected: Mapping = from_str(
+            "
+        app:
+            ports:
+                - target: 80
+                - target: 443
+            name: '1.10'
+        ",
+        )?;
+        assert_eq!(expected, values);
+
+        let err = set_yaml_string_as_json(&mut values, "app={invalid").unwrap_err();
+        assert_eq!(err.to_string(), "Failed to parse JSON value {invalid");
+        Ok(())
+    }
 }